@@ -0,0 +1,73 @@
+//! Measures inserts from several threads into one table, through handles from `get_table`.
+//!
+//! Run it with `cargo bench --bench concurrent_insert`.
+
+use std::path::Path;
+use std::thread;
+use std::time::Instant;
+
+use moodb::core::MooClient;
+use moodb::types::StorageTypes;
+use moodb::Configuration;
+
+const THREADS: usize = 8;
+const INSERTS_PER_THREAD: usize = 500;
+
+fn run(name: &str, config: Configuration) {
+    let mut db = MooClient::<usize>::new(name, Some(Path::new("db/bench")), Some(config)).unwrap();
+
+    db.reset_table(name).unwrap();
+
+    let started = Instant::now();
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|writer| {
+            let table = db.get_table().unwrap();
+
+            thread::spawn(move || {
+                for i in 0..INSERTS_PER_THREAD {
+                    let key = format!("{}-{}", writer, i);
+                    table.write().unwrap().insert(key, i).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let elapsed = started.elapsed();
+    let inserts = THREADS * INSERTS_PER_THREAD;
+
+    assert_eq!(db.get_table().unwrap().read().unwrap().len(), inserts);
+
+    println!(
+        "{}: {} inserts from {} threads in {:?} ({:.0} inserts/s)",
+        name,
+        inserts,
+        THREADS,
+        elapsed,
+        inserts as f64 / elapsed.as_secs_f64()
+    );
+
+    db.delete_table(name).unwrap();
+}
+
+fn main() {
+    run(
+        "concurrent_insert_memory",
+        Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        },
+    );
+
+    run(
+        "concurrent_insert_wal",
+        Configuration {
+            wal: true,
+            ..Configuration::default()
+        },
+    );
+}
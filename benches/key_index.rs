@@ -0,0 +1,47 @@
+//! Measures inserts and lookups by key on a large table, which go through the key index.
+//!
+//! Run it with `cargo bench --bench key_index`.
+
+use std::time::Instant;
+
+use moodb::core::MooClient;
+use moodb::types::StorageTypes;
+use moodb::Configuration;
+
+const RECORDS: u64 = 50_000;
+
+fn main() {
+    let config = Configuration {
+        storage: StorageTypes::Memory,
+        ..Configuration::default()
+    };
+
+    let mut db = MooClient::<u64, u64>::new("key_index", None, Some(config)).unwrap();
+    let table = db.get_table().unwrap();
+    let mut table = table.write().unwrap();
+
+    let started = Instant::now();
+
+    for i in 0..RECORDS {
+        table.insert(i, i * 2).unwrap();
+    }
+
+    let inserted = started.elapsed();
+    let started = Instant::now();
+
+    for i in 0..RECORDS {
+        assert_eq!(table.get(&i).unwrap(), i * 2);
+    }
+
+    let read = started.elapsed();
+
+    println!(
+        "key_index: {} inserts in {:?} ({:.0} inserts/s), {} gets in {:?} ({:.0} gets/s)",
+        RECORDS,
+        inserted,
+        RECORDS as f64 / inserted.as_secs_f64(),
+        RECORDS,
+        read,
+        RECORDS as f64 / read.as_secs_f64()
+    );
+}
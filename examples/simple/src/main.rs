@@ -1,5 +1,6 @@
 use moodb::{core::MooClient, Configuration};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct CryptoAccount {
@@ -13,7 +14,7 @@ const TABLE_NAME: &str = "crypto_accounts";
 fn main() {
 
     let config = Configuration {
-        db_dir: "./db/moo",
+        db_dir: PathBuf::from("./db/moo"),
         debug_mode: true,
         debug_level: None,
     };
@@ -27,11 +28,13 @@ fn main() {
     client.reset_table().unwrap();
 
     // Get our table from the database
-    let mut account_table = match client.get_table() {
+    let table = match client.get_table() {
         Ok(table) => table,
         Err(e) => panic!("Error getting table: {}", e.message),
     };
 
+    let mut account_table = table.write().unwrap();
+
     // Insert some accounts into the table
     for i in 0..100 {
         let account = CryptoAccount {
@@ -0,0 +1,298 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::core::MooTable;
+use crate::{MooError, MooErrorCodes, MooKey, MooRecords, MooResult};
+
+/// A table for async code, whose disk writes are awaited instead of blocking the runtime.
+///
+/// The records are changed in memory with the same logic as `MooTable`, then the table file is written
+/// with `tokio::fs`. Change events and the audit log are sent as soon as a change is applied in memory,
+/// before its write finishes. If a write fails the change stays in memory and is written by the next `save`.
+///
+/// The table is behind a `tokio::sync::RwLock`, so cloning it gives another handle to the same records.
+#[derive(Debug)]
+pub struct AsyncMooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    table: Arc<RwLock<MooTable<K, T>>>,
+}
+
+impl<K, T> Clone for AsyncMooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    fn clone(&self) -> Self {
+        Self {
+            table: Arc::clone(&self.table),
+        }
+    }
+}
+
+impl<K, T> AsyncMooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Creates an async table from a table opened by a `MooClient`.
+    ///
+    /// The `table` to wrap. Autosave is turned off on it, the async methods write the table file themselves.
+    pub fn new(mut table: MooTable<K, T>) -> Self {
+        table.config.autosave = false;
+
+        Self {
+            table: Arc::new(RwLock::new(table)),
+        }
+    }
+
+    /// Lock the table for reading, to use any of the sync methods that don't change it.
+    pub async fn read(&self) -> RwLockReadGuard<'_, MooTable<K, T>> {
+        self.table.read().await
+    }
+
+    /// Lock the table for writing, to use any of the sync methods.
+    ///
+    /// Changes made through the guard are only written to disk by `save`.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, MooTable<K, T>> {
+        self.table.write().await
+    }
+
+    /// Insert a new record into the table.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn insert(&self, key: impl Into<K>, value: T) -> MooResult<()> {
+        let mut table = self.table.write().await;
+
+        table.insert(key, value)?;
+
+        write_table(&mut table).await
+    }
+
+    /// Insert a record into the table, or update it if a record with the key already exists.
+    ///
+    /// The `key` of the record to upsert.
+    ///
+    /// The `value` of the record to upsert.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn upsert(&self, key: impl Into<K>, value: T) -> MooResult<()> {
+        let mut table = self.table.write().await;
+
+        table.upsert(key, value)?;
+
+        write_table(&mut table).await
+    }
+
+    /// Get a record from the table.
+    ///
+    /// The `key` of the record to get. An expired record returns `NotFound`.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn get<Q>(&self, key: &Q) -> MooResult<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let table = self.table.read().await;
+
+        table.record_get();
+        table.live_value(key).cloned()
+    }
+
+    /// Get all the records from the table.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn get_all(&self) -> MooResult<MooRecords<T, K>> {
+        let table = self.table.read().await;
+
+        let records: MooRecords<T, K> = table
+            .iter()
+            .filter(|record| record.is_live())
+            .cloned()
+            .collect();
+
+        if records.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: "No records found in the table.".to_string(),
+            });
+        }
+
+        Ok(records)
+    }
+
+    /// Update a record in the table.
+    ///
+    /// The `key` of the record to update.
+    ///
+    /// The `value` to update the record with.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn update<Q>(&self, key: &Q, value: T) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let mut table = self.table.write().await;
+
+        table.update(key, value)?;
+
+        write_table(&mut table).await
+    }
+
+    /// Delete a record from the table.
+    ///
+    /// The `key` of the record to delete.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn delete<Q>(&self, key: &Q) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let mut table = self.table.write().await;
+
+        table.delete(key)?;
+
+        write_table(&mut table).await
+    }
+
+    /// Whether a record with the `key` is in the table.
+    pub async fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.table.read().await.contains_key(key)
+    }
+
+    /// The number of records in the table.
+    pub async fn len(&self) -> usize {
+        self.table.read().await.len()
+    }
+
+    /// Whether the table has no records.
+    pub async fn is_empty(&self) -> bool {
+        self.table.read().await.is_empty()
+    }
+
+    /// Write all in-memory changes to disk, like the changes made through `write`.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub async fn save(&self) -> MooResult<()> {
+        let mut table = self.table.write().await;
+
+        write_table(&mut table).await
+    }
+}
+
+/// Writes the records of a table to its file with `tokio::fs`.
+///
+/// The records are written to a temporary file first, which is then renamed over the table file.
+///
+/// This is an internal function and can't be used directly by the user.
+async fn write_table<K, T>(table: &mut MooTable<K, T>) -> MooResult<()>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    let started = Instant::now();
+
+    let pending = match table.prepare_write()? {
+        Some(pending) => pending,
+        None => return Ok(()),
+    };
+
+    let mut temp_file = match File::create(&pending.temp_path).await {
+        Ok(temp_file) => temp_file,
+        Err(_) => {
+            return Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: "Failed to create temporary table file. Might be missing permissions to write the directory?".to_string(),
+            })
+        }
+    };
+
+    temp_file.write_all(&pending.serialized_records).await?;
+    temp_file.sync_all().await?;
+
+    drop(temp_file);
+
+    fs::rename(&pending.temp_path, &pending.file_path).await?;
+
+    table.finish_write()?;
+    table.record_save(started.elapsed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::MooClient;
+    use crate::types::StorageTypes;
+    use crate::{Configuration, MooErrorCodes};
+
+    #[tokio::test]
+    async fn test_async_table() {
+        let mut db = MooClient::<u32>::new("test_async_table", None, None).unwrap();
+
+        db.reset_table("test_async_table").unwrap();
+
+        let table = db.into_table().unwrap().into_async();
+
+        table.insert("1", 1).await.unwrap();
+        table.insert("2", 2).await.unwrap();
+        table.update("1", 10).await.unwrap();
+        table.delete("2").await.unwrap();
+
+        assert_eq!(table.get("1").await.unwrap(), 10);
+        assert_eq!(
+            table.get("2").await.unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+
+        table.write().await.upsert("3", 3).unwrap();
+        assert!(table.read().await.is_dirty());
+
+        table.save().await.unwrap();
+        assert!(!table.read().await.is_dirty());
+
+        let reopened = MooClient::<u32>::new("test_async_table", None, None)
+            .unwrap()
+            .into_table()
+            .unwrap();
+
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.all()[0].value, 10);
+
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Default::default()
+        };
+
+        let memory = MooClient::<u32>::new("test_async_memory", None, Some(config))
+            .unwrap()
+            .into_table()
+            .unwrap()
+            .into_async();
+
+        memory.insert("1", 1).await.unwrap();
+
+        assert_eq!(memory.len().await, 1);
+        assert!(!memory.read().await.is_dirty());
+    }
+}
@@ -1,30 +1,62 @@
-use serde::de::DeserializeOwned;
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::Serialize;
-use std::fs::{File, OpenOptions};
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::hash::Hash;
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::sync::{Arc, Mutex};
-use std::{fs, path::PathBuf};
+use std::ops::{Deref, DerefMut, RangeBounds};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::utils::debug::DebugClient;
+use chrono::Utc;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "tokio")]
+use crate::async_core::AsyncMooTable;
+use crate::format::{self, Serializer};
+use crate::index::NumericIndex;
+use crate::types::{
+    BatchReport, Compression, ConflictPolicy, ImportMode, RunMode, SerializationFormat,
+    StorageTypes, TableMetrics, TableStats,
+};
+use crate::utils::audit::AuditLog;
+use crate::utils::debug::{DebugClient, DebugLevel};
+use crate::utils::wal::{WalEntry, WriteAheadLog};
 use crate::{
-    Configuration, MooError, MooErrorCodes, MooRecord, MooRecords, MooResult, DEFAULT_DIR,
-    FILE_EXTENSION,
+    ChangeEvent, Configuration, Counter, MooError, MooErrorCodes, MooKey, MooRecord, MooRecords,
+    MooResult,
 };
 
 /// The main database client.
 ///
 /// This struct is used to create a new database instance
 /// and perform actions on the database.
+///
+/// The records in its tables are keyed by `String` unless another key type `K` is given.
 #[derive(Debug, Clone)]
-pub struct MooClient<T>
+pub struct MooClient<T, K = String>
 where
     T: Clone + Serialize + DeserializeOwned,
+    K: MooKey,
 {
     /// The path to the directory where the database and its tables are stored.
     pub path: PathBuf,
 
-    /// The table for this database instance.
-    pub table: MooTable<T>,
+    /// The table for this database instance, shared with every handle returned by `get_table`.
+    pub table: SharedMooTable<K, T>,
+
+    /// The other tables opened or created through this database instance, by name.
+    pub tables: HashMap<String, SharedMooTable<K, T>>,
+
+    /// The name of the table for this database instance, so it can be compared without locking the table.
+    name: String,
 
     /// The configuration for this database instance.
     pub config: Configuration,
@@ -33,38 +65,31 @@ where
     pub debugger: DebugClient,
 }
 
-impl<T> MooClient<T>
+impl<T, K> MooClient<T, K>
 where
     T: Clone + Serialize + DeserializeOwned,
+    K: MooKey,
 {
     /// Creates a new Moo database instance.
     ///
     /// The `name` of the table for this database instance is required.
     ///
     /// Pass the `path` to the directory where the database and its tables will be stored.
-    /// If non is passed, the database will be stored in a default directory called `moodb` in the current working directory.
+    /// If none is passed, the `db_dir` of the config is used, which defaults to `db/moo` in the current working directory.
     ///
     /// Returns a `MooResult` with the result of the action.
     pub fn new(
         name: &str,
-        dir: Option<&str>,
+        dir: Option<&Path>,
         config: Option<Configuration>,
-    ) -> MooResult<MooClient<T>> {
-        println!("MooDB Initializing...");
-
-        let config = match config {
-            Some(config) => config,
-            None => Configuration::default(),
-        };
+    ) -> MooResult<MooClient<T, K>> {
+        let config = config.unwrap_or_default();
 
         let config_clone = config.clone();
 
-        let path = match dir {
-            Some(dir) => PathBuf::from(dir),
-            None => PathBuf::from(format!("./{}", DEFAULT_DIR)),
-        };
+        let path = resolve_dir(dir, &config);
 
-        if !path.exists() {
+        if config.storage != StorageTypes::Memory && !path.exists() {
             match fs::create_dir_all(&path) {
                 Ok(_) => {},
                 Err(_) => {
@@ -76,7 +101,7 @@ where
             }
         }
 
-        let _debugger = DebugClient::new(config.debug_mode, None, config_clone);
+        let _debugger = DebugClient::new(config.debug_mode, config.debug_level, config_clone);
 
         let table = match MooTable::new(name, &path, config.clone(), _debugger.clone()) {
             Ok(table) => table,
@@ -85,596 +110,8148 @@ where
             }
         };
 
-        println!("MooDB Initialized.");
+        _debugger.log(
+            DebugLevel::Info,
+            format!("MooDB initialized with table: {}", name),
+        );
 
         Ok(Self {
             path,
-            table,
+            table: table.into_shared(),
+            tables: HashMap::new(),
+            name: name.to_string(),
             config,
             debugger: _debugger,
         })
     }
 
-    /// Reset the table file and clear all records.
-    pub fn reset_table(&mut self) -> MooResult<()> {
-        self.debugger
-            .log(format!("Resetting table: {}", self.table.name));
+    /// Creates a new Moo database instance configured by a config file.
+    ///
+    /// The `name` of the table for this database instance is required.
+    ///
+    /// The `config_path` to a `.toml` or `.json` file with the `Configuration`.
+    /// The database is stored in the `db_dir` from the config file.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn from_config_file<P: AsRef<Path>>(
+        name: &str,
+        config_path: P,
+    ) -> MooResult<MooClient<T, K>> {
+        let config = Configuration::from_file(config_path)?;
+        let dir = config.db_dir.clone();
 
-        self.table.records.clear();
+        Self::new(name, Some(&dir), Some(config))
+    }
 
-        let mut file = match self.table.file.lock() {
-            Ok(file) => file,
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to lock table file.".to_string(),
-                })
-            }
-        };
+    /// Opens an existing Moo database instance or creates and seeds a new one.
+    ///
+    /// Takes the same `name`, `dir` and `config` as `new`.
+    ///
+    /// The `seed` closure is only run when the table file doesn't exist yet, so it can fill a new table with default data.
+    /// If seeding fails the new table file is removed again, so the next call will retry it.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn open_or_init<F>(
+        name: &str,
+        dir: Option<&Path>,
+        config: Option<Configuration>,
+        seed: F,
+    ) -> MooResult<MooClient<T, K>>
+    where
+        F: FnOnce(&mut MooTable<K, T>) -> MooResult<()>,
+    {
+        let naming = config.clone().unwrap_or_default();
+        let exists = naming.storage != StorageTypes::Memory
+            && find_table_file(&resolve_dir(dir, &naming), name, &naming).is_some();
 
-        match file.seek(SeekFrom::Start(0)) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to seek table file.".to_string(),
-                })
-            }
-        }
+        let mut client = Self::new(name, dir, config)?;
 
-        match file.write_all(&[]) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to write to table file.".to_string(),
-                })
-            }
-        }
+        if !exists {
+            client.debugger.log(
+                DebugLevel::Info,
+                format!("Seeding new table: {}", client.name),
+            );
 
-        match file.set_len(0) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to truncate table file.".to_string(),
-                })
-            }
-        }
+            let seeded = seed(&mut *write_shared(&client.table)?);
 
-        match file.flush() {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to flush table file.".to_string(),
-                })
+            if let Err(err) = seeded {
+                client.delete_table(name)?;
+                return Err(err);
             }
         }
 
-        Ok(())
+        Ok(client)
     }
 
-    /// Get a table from the database.
+    /// Reset a table file and clear all its records. (Keeps the table file.)
     ///
-    /// Pass the `name` of the table to get.
+    /// The `name` of the table to reset.
     ///
     /// Returns a `MooResult` with the result of the action.
-    pub fn get_table(&mut self) -> MooResult<MooTable<T>> {
+    pub fn reset_table(&mut self, name: &str) -> MooResult<()> {
         self.debugger
-            .log(format!("Getting table: {}", self.table.name));
+            .log(DebugLevel::Info, format!("Resetting table: {}", name));
 
-        Ok(self.table.clone())
+        write_shared(&self.shared_table(name)?)?.reset()
     }
 
-    /// Delete the table file itself.
+    /// Create a new table in the database directory.
     ///
-    /// Returns a `MooResult` with the result true if the table was deleted, false if it was not or an error if something went wrong.
-    pub fn delete_table(&mut self) -> MooResult<()> {
+    /// The `name` of the table to create. It gets its own file next to the other tables.
+    ///
+    /// Returns a `MooResult` with a handle to the new table, or an error if the table already exists.
+    pub fn create_table(&mut self, name: &str) -> MooResult<SharedMooTable<K, T>> {
+        if name == self.name || self.tables.contains_key(name) || self.table_file_exists(name) {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: format!(
+                    "Table with name: {} already exists. Use open_table to open it.",
+                    name
+                ),
+            });
+        }
+
         self.debugger
-            .log(format!("Deleting table: {}", self.table.name));
+            .log(DebugLevel::Info, format!("Creating table: {}", name));
 
-        match self.table.delete_self(&self.path) {
-            Ok(_) => Ok(()),
-            Err(err) => Err(err),
-        }
-    }
-}
+        let table = MooTable::new(name, &self.path, self.config.clone(), self.debugger.clone())?
+            .into_shared();
 
-/// The database table containing records.
-#[derive(Debug, Clone)]
-pub struct MooTable<T>
-where
-    T: Clone + Serialize + DeserializeOwned,
-{
-    pub name: String,
-    pub file: Arc<Mutex<File>>,
-    pub records: MooRecords<T>,
-    pub config: Configuration,
-    pub debugger: DebugClient,
-}
+        self.tables.insert(name.to_string(), Arc::clone(&table));
 
-impl<T> MooTable<T>
-where
-    T: Clone + Serialize + DeserializeOwned,
-{
-    /// Creates a new table for a database instance.
-    ///
-    /// The `name` of the table to create.
+        Ok(table)
+    }
+
+    /// Open an existing table in the database directory.
     ///
-    /// The `path` to the directory where the table will be stored.
+    /// The `name` of the table to open.
     ///
-    /// This is an internal function and can't be used directly by the user.
-    fn new(
-        name: &str,
-        path: &PathBuf,
-        config: Configuration,
-        debugger: DebugClient,
-    ) -> MooResult<MooTable<T>> {
-        let file_path = path.join(format!("{}.{}", name, FILE_EXTENSION));
+    /// Returns a `MooResult` with a handle to the table, or `NotFound` if the table doesn't exist.
+    pub fn open_table(&mut self, name: &str) -> MooResult<SharedMooTable<K, T>> {
+        self.debugger
+            .log(DebugLevel::Info, format!("Opening table: {}", name));
 
-        let mut file = match OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&file_path) {
-            Ok(file) => file,
-            Err(_) => {
-                match File::create(&file_path) {
-                    Ok(file) => file,
-                    Err(_) => {
-                        return Err(MooError {
-                            code: MooErrorCodes::Fatal,
-                            message: "Failed to create table file. Might be missing permissions to write the directory?".to_string()
-                        })
-                    }
-                }
-            }
-        };
+        self.shared_table(name)
+    }
 
-        let mut contents = Vec::new();
+    /// List the names of all tables in the database directory, sorted by name.
+    ///
+    /// With `StorageTypes::Memory` only the tables opened through this client are listed.
+    ///
+    /// Returns a `MooResult` with the table names.
+    pub fn list_tables(&self) -> MooResult<Vec<String>> {
+        if self.config.storage == StorageTypes::Memory {
+            let mut names: Vec<String> = self.tables.keys().cloned().collect();
+            names.push(self.name.clone());
+            names.sort();
 
-        match file.read_to_end(&mut contents) {
-            Ok(_) => {}
+            return Ok(names);
+        }
+
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
             Err(_) => {
                 return Err(MooError {
                     code: MooErrorCodes::Fatal,
-                    message: "Failed to read table file.".to_string(),
+                    message: "Failed to read database directory.".to_string(),
                 })
             }
-        }
-
-        let records: Vec<MooRecord<T>> = if contents.is_empty() {
-            Vec::new()
-        } else {
-            let cloned_contents = contents.clone(); // Create a clone for deserialization
-            match serde_json::from_slice(&cloned_contents) {
-                Ok(records) => records,
-                Err(_) => {
-                    return Err(MooError {
-                        code: MooErrorCodes::Error,
-                        message: "Failed to parse table file.".to_string(),
-                    })
-                }
-            }
         };
 
-        Ok(Self {
-            name: name.to_string(),
-            file: Arc::new(Mutex::new(file)),
-            records,
-            config,
-            debugger,
-        })
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| table_name(&entry.path(), &self.config))
+            .map(|(name, _, _)| name)
+            .collect();
+
+        names.sort();
+        names.dedup();
+
+        Ok(names)
     }
 
-    /// Deletes this table from the database instance.
+    /// Gets the shared handle of a table bound to this client by name, loading it from its file if it isn't open yet.
     ///
     /// This is an internal function and can't be used directly by the user.
-    fn delete_self(&mut self, path: &PathBuf) -> MooResult<()> {
-        self.records.clear();
+    fn shared_table(&mut self, name: &str) -> MooResult<SharedMooTable<K, T>> {
+        if name == self.name {
+            return Ok(Arc::clone(&self.table));
+        }
 
-        let file_path = path.join(format!("{}.{}", self.name, FILE_EXTENSION));
+        if let Some(table) = self.tables.get(name) {
+            return Ok(Arc::clone(table));
+        }
 
-        match fs::remove_file(&file_path) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(MooError {
-                code: MooErrorCodes::Fatal,
-                message: format!("Failed to delete table file: {}. Might be missing permissions to delete the file.", self.name),
-            })
+        if !self.table_file_exists(name) {
+            return Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No table found with name: {}", name),
+            });
         }
+
+        let table = MooTable::new(name, &self.path, self.config.clone(), self.debugger.clone())?
+            .into_shared();
+
+        self.tables.insert(name.to_string(), Arc::clone(&table));
+
+        Ok(table)
     }
 
-    /// Saves the table to disk after an action.
+    /// Whether a table file with the `name` exists in the database directory. Memory tables never have one.
     ///
     /// This is an internal function and can't be used directly by the user.
-    fn save(&self) -> MooResult<()> {
-        let serialized_records = match serde_json::to_vec(&self.records) {
-            Ok(serialized_records) => serialized_records,
+    fn table_file_exists(&self, name: &str) -> bool {
+        self.config.storage != StorageTypes::Memory
+            && find_table_file(&self.path, name, &self.config).is_some()
+    }
+
+    /// Get a table from the database.
+    ///
+    /// Every call returns a handle to the same table, so the records aren't copied and a change made through one
+    /// handle is seen by all the others and by the client, even for tables stored in memory.
+    /// Lock the handle with `read` or `write` to use the table.
+    ///
+    /// Returns a `MooResult` with a `SharedMooTable` of the table.
+    pub fn get_table(&mut self) -> MooResult<SharedMooTable<K, T>> {
+        self.debugger
+            .log(DebugLevel::Info, format!("Getting table: {}", self.name));
+
+        Ok(Arc::clone(&self.table))
+    }
+
+    /// Take the table out of the client, like to move it into an `AsyncMooTable` with `into_async`.
+    ///
+    /// Returns a `MooResult` with the table, or an error if a handle from `get_table` still uses it.
+    pub fn into_table(self) -> MooResult<MooTable<K, T>> {
+        let table = match Arc::try_unwrap(self.table) {
+            Ok(table) => table,
             Err(_) => {
                 return Err(MooError {
-                    code: MooErrorCodes::Error,
-                    message: "Failed to serialize table records.".to_string(),
+                    code: MooErrorCodes::Warn,
+                    message: format!("Table: {} is still used by another handle.", self.name),
                 })
             }
         };
 
-        let mut file = match self.file.lock() {
-            Ok(file) => file,
+        match table.into_inner() {
+            Ok(table) => Ok(table),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: "Failed to lock shared table.".to_string(),
+            }),
+        }
+    }
+
+    /// Get the stats of every table in the database directory.
+    ///
+    /// Tables other than the one bound to this client are read from their files.
+    /// With `StorageTypes::Memory` only the tables opened through this client exist.
+    ///
+    /// Returns a `MooResult` with the stats of every table by table name.
+    pub fn stats_all(&self) -> MooResult<HashMap<String, TableStats>> {
+        if self.config.storage == StorageTypes::Memory {
+            let mut stats = HashMap::new();
+
+            stats.insert(self.name.clone(), read_shared(&self.table)?.stats()?);
+
+            for (name, table) in &self.tables {
+                stats.insert(name.clone(), read_shared(table)?.stats()?);
+            }
+
+            return Ok(stats);
+        }
+
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
             Err(_) => {
                 return Err(MooError {
                     code: MooErrorCodes::Fatal,
-                    message: "Failed to lock table file.".to_string(),
+                    message: "Failed to read database directory.".to_string(),
                 })
             }
         };
 
-        match file.seek(SeekFrom::Start(0)) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to seek table file.".to_string(),
-                })
+        let mut stats = HashMap::new();
+
+        for entry in entries.flatten() {
+            let file_path = entry.path();
+
+            let (name, format, compression) = match table_name(&file_path, &self.config) {
+                Some(table) => table,
+                None => continue,
+            };
+
+            if name == self.name {
+                stats.insert(name, read_shared(&self.table)?.stats()?);
+                continue;
             }
-        }
 
-        match file.write_all(&serialized_records) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to write to table file.".to_string(),
-                })
+            if let Some(table) = self.tables.get(&name) {
+                stats.insert(name, read_shared(table)?.stats()?);
+                continue;
             }
+
+            let contents = match fs::read(&file_path) {
+                Ok(contents) => contents,
+                Err(_) => {
+                    return Err(MooError {
+                        code: MooErrorCodes::Fatal,
+                        message: format!("Failed to read table file: {}", name),
+                    })
+                }
+            };
+
+            let contents = match format::strip_checksum(&contents)? {
+                Some(bytes) => bytes.to_vec(),
+                None => contents,
+            };
+
+            let contents = format::strip_schema_version(&contents)?.1.to_vec();
+
+            let contents = match &self.config.encryption_key {
+                Some(key) if !contents.is_empty() => format::decrypt(key, &contents)?,
+                _ => contents,
+            };
+
+            let contents = match compression {
+                Some(compression) if !contents.is_empty() => compression.decompress(&contents)?,
+                _ => contents,
+            };
+
+            let parsed = if contents.is_empty() {
+                Ok(0)
+            } else if format == SerializationFormat::Bincode {
+                // Bincode isn't self-describing, so its records have to be decoded to be counted.
+                format
+                    .deserialize::<MooRecords<T, K>>(&contents)
+                    .map(|records| records.len())
+            } else {
+                format
+                    .deserialize::<Vec<IgnoredAny>>(&contents)
+                    .map(|records| records.len())
+            };
+
+            let records = match parsed {
+                Ok(records) => records,
+                Err(_) => {
+                    return Err(MooError {
+                        code: MooErrorCodes::Error,
+                        message: format!("Failed to parse table file: {}", name),
+                    })
+                }
+            };
+
+            let metadata = entry.metadata().ok();
+
+            stats.insert(
+                name,
+                TableStats {
+                    records,
+                    size: metadata.as_ref().map_or(0, |metadata| metadata.len()),
+                    modified: metadata.and_then(|metadata| metadata.modified().ok()),
+                },
+            );
         }
 
-        match file.set_len(serialized_records.len() as u64) {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(MooError {
-                    code: MooErrorCodes::Fatal,
-                    message: "Failed to truncate table file.".to_string(),
-                })
+        Ok(stats)
+    }
+
+    /// Get the size of every table file in the database directory added together, in bytes.
+    ///
+    /// With `StorageTypes::Memory` the serialized sizes of the tables opened through this client are added instead.
+    ///
+    /// Returns a `MooResult` with the total size in bytes.
+    pub fn total_size(&self) -> MooResult<u64> {
+        if self.config.storage == StorageTypes::Memory {
+            let mut size = read_shared(&self.table)?.size_on_disk()?;
+
+            for table in self.tables.values() {
+                size += read_shared(table)?.size_on_disk()?;
             }
+
+            return Ok(size);
         }
 
-        match file.flush() {
-            Ok(_) => {}
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
             Err(_) => {
                 return Err(MooError {
                     code: MooErrorCodes::Fatal,
-                    message: "Failed to flush table file.".to_string(),
+                    message: "Failed to read database directory.".to_string(),
                 })
             }
-        }
+        };
 
-        Ok(())
+        Ok(entries
+            .flatten()
+            .filter(|entry| table_name(&entry.path(), &self.config).is_some())
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum())
     }
 
-    /// Insert a new record into the table.
+    /// Delete a table file itself.
     ///
-    /// The `key` of the record to insert.
+    /// The `name` of the table to delete.
     ///
-    /// The `value` of the record to insert.
-    pub fn insert(&mut self, key: &str, value: T) -> MooResult<()> {
-        let exist = match self.get(key) {
-            Ok(_) => true,
-            Err(_) => false,
-        };
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete_table(&mut self, name: &str) -> MooResult<()> {
+        self.debugger
+            .log(DebugLevel::Warning, format!("Deleting table: {}", name));
 
-        if exist {
-            return Err(MooError {
-                code: MooErrorCodes::Warn,
-                message: format!("Record with key: {} already exists. Use the update method to change its value.", key),
-            });
-        }
+        write_shared(&self.shared_table(name)?)?.delete_self()?;
+        self.tables.remove(name);
 
-        let record = MooRecord {
-            key: key.to_string(),
-            value,
-        };
+        Ok(())
+    }
 
-        self.records.push(record);
+    /// Reset every table in the database directory, like `reset_table` on each of them.
+    ///
+    /// Only files with the extension of a table format are touched, other files in the directory are left alone.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn reset_all_tables(&mut self) -> MooResult<()> {
+        for name in self.list_tables()? {
+            self.reset_table(&name)?;
+        }
 
-        match self.save() {
-            Ok(_) => {}
-            Err(err) => {
-                return Err(err);
-            }
-        }
+        Ok(())
+    }
 
-        self.debugger
-            .log(format!("Insert new record with key: {}", key));
+    /// Delete every table file in the database directory, like `delete_table` on each of them.
+    ///
+    /// Only files with the extension of a table format are deleted, other files in the directory are left alone.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete_all_tables(&mut self) -> MooResult<()> {
+        for name in self.list_tables()? {
+            self.delete_table(&name)?;
+        }
 
         Ok(())
     }
+}
 
-    pub fn insert_many(&mut self, data: MooRecords<T>) -> MooResult<()> {
-        if data.is_empty() {
-            return Err(MooError {
-                code: MooErrorCodes::Warn,
-                message: "No records to insert.".to_string(),
-            });
-        }
+/// A database client whose table stores every value behind an `Arc`.
+///
+/// Values are deserialized once on load and reading them with `get` or `get_all` only clones the `Arc`,
+/// which avoids deep copies of large values in read heavy workloads.
+#[cfg(feature = "arc-values")]
+pub type ArcMooClient<T> = MooClient<Arc<T>>;
 
-        for record in &data {
-            let exist = match self.get(&record.key) {
-                Ok(_) => true,
-                Err(_) => false,
-            };
+/// A table storing every value behind an `Arc`. See `ArcMooClient`.
+#[cfg(feature = "arc-values")]
+pub type ArcMooTable<T> = MooTable<String, Arc<T>>;
 
-            if exist {
-                return Err(MooError {
-                    code: MooErrorCodes::Warn,
-                    message: format!("Record with key: {} already exists. Use the update method to change its value.", record.key),
-                });
-            }
-        }
+/// The part of a snapshot file name between the table name and the snapshot label.
+const SNAPSHOT_INFIX: &str = ".snapshot.";
 
-        for record in &data {
-            self.records.push(record.clone());
-            self.debugger
-                .log(format!("Insert new record with key: {}", record.key));
-        }
+/// Gets the directory the database is stored in, falling back to the `db_dir` of the config.
+fn resolve_dir(dir: Option<&Path>, config: &Configuration) -> PathBuf {
+    match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => config.db_dir.clone(),
+    }
+}
 
-        match self.save() {
-            Ok(_) => {}
-            Err(err) => {
-                return Err(err);
-            }
-        }
+/// Gets the name of the files of a table without their extensions, the table name after the configured prefix.
+fn file_stem(config: &Configuration, name: &str) -> String {
+    format!(
+        "{}{}",
+        config.file_prefix.as_deref().unwrap_or_default(),
+        name
+    )
+}
 
-        Ok(())
+/// Gets the extension of table files in a format, unless the configuration overrides it.
+fn file_extension(config: &Configuration, format: SerializationFormat) -> &str {
+    config
+        .file_extension_override
+        .as_deref()
+        .unwrap_or(format.extension())
+}
+
+/// Gets the path of the file a table is stored in.
+fn table_file_path(
+    path: &Path,
+    name: &str,
+    config: &Configuration,
+    format: SerializationFormat,
+    compression: Option<Compression>,
+) -> PathBuf {
+    let mut file_name = format!(
+        "{}.{}",
+        file_stem(config, name),
+        file_extension(config, format)
+    );
+
+    if let Some(compression) = compression {
+        file_name.push('.');
+        file_name.push_str(compression.extension());
     }
 
-    /// Get a record from the table.
-    ///
-    /// The `key` of the record to get.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn get(&mut self, key: &str) -> MooResult<T> {
-        for record in &self.records {
-            if record.key == key {
-                self.debugger.log(format!("Found record with key: {}", key));
+    path.join(file_name)
+}
 
-                return Ok(record.value.clone());
-            }
-        }
+/// Finds the existing file a table is stored in, in any serialization format and compression.
+///
+/// With an overridden extension the format can't be told from the file name, so only the configured format is used.
+fn find_table_file(
+    path: &Path,
+    name: &str,
+    config: &Configuration,
+) -> Option<(PathBuf, SerializationFormat, Option<Compression>)> {
+    let formats = match config.file_extension_override {
+        Some(_) => vec![config.format],
+        None => SerializationFormat::ALL.to_vec(),
+    };
 
-        Err(MooError {
-            code: MooErrorCodes::NotFound,
-            message: format!("No record found with key: {}", key),
+    formats
+        .into_iter()
+        .flat_map(|format| [(format, None), (format, Some(Compression::Gzip))])
+        .map(|(format, compression)| {
+            (
+                table_file_path(path, name, config, format, compression),
+                format,
+                compression,
+            )
         })
-    }
+        .find(|(file_path, _, _)| file_path.exists())
+}
 
-    /// Get multiple records from the table at once.
-    ///
-    /// The `keys` of the records to get.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn get_many(&mut self, keys: Vec<&str>) -> MooResult<MooRecords<T>> {
-        let mut records = Vec::new();
+/// Gets the path of the temporary file a table is written to before it replaces the table file.
+fn temp_file_path(file_path: &Path) -> PathBuf {
+    let mut temp_path = file_path.as_os_str().to_owned();
+    temp_path.push(".tmp");
 
-        for record in &self.records {
-            if keys.contains(&record.key.as_str()) {
-                records.push(record.clone());
-                self.debugger
-                    .log(format!("Found record with key: {}", record.key));
-            }
-        }
+    PathBuf::from(temp_path)
+}
 
-        if records.is_empty() {
+/// Writes the `bytes` to a temporary file next to the `path` first, which is then renamed over it.
+///
+/// The rename is atomic, so a crash in the middle of a write leaves the previous file intact.
+fn write_atomic(path: &Path, bytes: &[u8]) -> MooResult<()> {
+    let temp_path = temp_file_path(path);
+
+    let mut temp_file = match File::create(&temp_path) {
+        Ok(temp_file) => temp_file,
+        Err(_) => {
             return Err(MooError {
-                code: MooErrorCodes::NotFound,
-                message: format!("No records found with keys: {:?}", keys),
-            });
+                code: MooErrorCodes::Fatal,
+                message: "Failed to create temporary table file. Might be missing permissions to write the directory?".to_string(),
+            })
         }
+    };
 
-        Ok(records)
+    temp_file.write_all(bytes)?;
+    temp_file.sync_all()?;
+
+    drop(temp_file);
+
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Opens the current table file at a path again for reading and writing.
+fn reopen_file(file_path: &Path) -> MooResult<File> {
+    match OpenOptions::new().read(true).write(true).open(file_path) {
+        Ok(file) => Ok(file),
+        Err(_) => Err(MooError {
+            code: MooErrorCodes::Fatal,
+            message: "Failed to reopen table file.".to_string(),
+        }),
     }
+}
 
-    /// Get all the records from the table.
-    ///
-    /// This should be extremely fast as the records are already loaded into memory.
-    /// You can also simply access the `records` field directly, however this method is provided for convenience
-    /// and it supports error handling and logging out of the box. Also keep in mind data should never be modified directly
-    /// using this property as it can cause data corruption with the file persistence.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn get_all(&mut self) -> MooResult<MooRecords<T>> {
-        if self.records.is_empty() {
+/// Takes an exclusive lock on the lock file of a table, creating the file if it doesn't exist yet.
+///
+/// The lock is advisory and held until the returned file is closed.
+fn lock_table(lock_path: &Path, name: &str) -> MooResult<File> {
+    let file = match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)
+    {
+        Ok(file) => file,
+        Err(_) => {
             return Err(MooError {
-                code: MooErrorCodes::NotFound,
-                message: "No records found in the table.".to_string(),
-            });
+                code: MooErrorCodes::Fatal,
+                message: "Failed to create table lock file. Might be missing permissions to write the directory?".to_string(),
+            })
         }
+    };
 
-        self.debugger
-            .log(format!("Found {} records", self.records.len()));
-
-        Ok(self.records.clone())
+    match file.try_lock() {
+        Ok(_) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(MooError {
+            code: MooErrorCodes::Error,
+            message: format!(
+                "Table: {} is already opened by another process or client.",
+                name
+            ),
+        }),
+        Err(TryLockError::Error(err)) => Err(err.into()),
     }
+}
 
-    /// Update a record in the table.
-    ///
-    /// The `key` of the record to update.
-    ///
-    /// The `value` of the record to update.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn update(&mut self, key: &str, value: T) -> MooResult<()> {
-        let mut index = 0;
+/// Removes duplicated keys from a list of keys, keeping the first occurrence of every key.
+///
+/// Bulk methods use this so their key lists behave as a set.
+fn dedupe_keys<'a, Q>(keys: &[&'a Q]) -> Vec<&'a Q>
+where
+    Q: Hash + Eq + ?Sized,
+{
+    let mut seen = HashSet::new();
 
-        for record in &self.records {
-            if record.key == key {
-                self.records[index].value = value;
-                self.save()?;
+    keys.iter()
+        .copied()
+        .filter(|key| seen.insert(*key))
+        .collect()
+}
+
+/// Whether the `text` matches a glob `pattern`, where `*` matches any number of characters and `?` exactly one.
+///
+/// After a mismatch the last `*` takes one more character, so this never backtracks further than that.
+fn glob_match(pattern: &[char], text: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
 
-                self.debugger
-                    .log(format!("Updated record with key: {}", key));
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
 
-                return Ok(());
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
             }
-
-            index += 1;
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
         }
-
-        Err(MooError {
-            code: MooErrorCodes::NotFound,
-            message: format!("No record found with key: {}", key),
-        })
     }
 
-    /// Update multiple records in the table at once.
-    ///
-    /// The `update` vector containing the records to update.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn update_many(&mut self, update: MooRecords<T>) -> MooResult<()> {
-        if update.is_empty() {
-            return Err(MooError {
-                code: MooErrorCodes::Warn,
-                message: "No records to update.".to_string(),
-            });
-        }
+    pattern[p..].iter().all(|&c| c == '*')
+}
 
-        for record in &mut self.records {
-            for update_record in &update {
-                if record.key == update_record.key {
-                    record.value = update_record.value.clone();
-                    self.debugger
-                        .log(format!("Updated record with key: {}", record.key));
-                }
-            }
-        }
+/// Locks a shared table for reading.
+fn read_shared<K, T>(
+    shared: &SharedMooTable<K, T>,
+) -> MooResult<RwLockReadGuard<'_, MooTable<K, T>>>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    match shared.read() {
+        Ok(table) => Ok(table),
+        Err(_) => Err(MooError {
+            code: MooErrorCodes::Fatal,
+            message: "Failed to lock shared table.".to_string(),
+        }),
+    }
+}
 
-        match self.save() {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                return Err(err);
-            }
-        }
+/// Locks a shared table for writing.
+fn write_shared<K, T>(
+    shared: &SharedMooTable<K, T>,
+) -> MooResult<RwLockWriteGuard<'_, MooTable<K, T>>>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    match shared.write() {
+        Ok(table) => Ok(table),
+        Err(_) => Err(MooError {
+            code: MooErrorCodes::Fatal,
+            message: "Failed to lock shared table.".to_string(),
+        }),
     }
+}
 
-    /// Delete a record from the table.
-    ///
-    /// The `key` of the record to delete.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn delete(&mut self, key: &str) -> MooResult<()> {
-        let mut index = 0;
+/// Formats a JSON value as a CSV cell. Strings are written as is, nested values as JSON and `null` as an empty cell.
+///
+/// Cells with a comma, quote or line break are quoted, with the quotes inside doubled.
+fn csv_cell(value: &serde_json::Value) -> String {
+    let cell = match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
 
-        for record in &self.records {
-            if record.key == key {
-                self.records.remove(index);
-                self.save()?;
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell
+    }
+}
+
+/// Gets the name, format and compression of the table stored in a file, or `None` if it isn't a table file.
+///
+/// Only files with the configured prefix and extension are tables.
+/// Snapshot files have the extension of their table, but aren't tables themselves.
+fn table_name(
+    file_path: &Path,
+    config: &Configuration,
+) -> Option<(String, SerializationFormat, Option<Compression>)> {
+    let file_name = file_path.file_name()?.to_str()?;
 
-                self.debugger
-                    .log(format!("Deleted record with key: {}", key));
+    let (file_name, compression) = match file_name.strip_suffix(".gz") {
+        Some(file_name) => (file_name, Some(Compression::Gzip)),
+        None => (file_name, None),
+    };
 
-                return Ok(());
-            }
+    let (stem, format) = match &config.file_extension_override {
+        Some(extension) => (
+            file_name
+                .strip_suffix(extension.as_str())?
+                .strip_suffix('.')?,
+            config.format,
+        ),
+        None => {
+            let (stem, extension) = file_name.rsplit_once('.')?;
 
-            index += 1;
+            (stem, SerializationFormat::from_extension(extension)?)
         }
+    };
 
-        Err(MooError {
-            code: MooErrorCodes::NotFound,
-            message: format!("No record found with key: {}", key),
-        })
+    let name = stem.strip_prefix(config.file_prefix.as_deref().unwrap_or_default())?;
+
+    if name.is_empty() || name.contains(SNAPSHOT_INFIX) {
+        return None;
     }
 
-    /// Deletes multiple records from the table at once.
-    ///
-    /// The `keys` of the records to delete.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn delete_many(&mut self, keys: Vec<&str>) -> MooResult<()> {
-        self.debugger
-            .log(format!("Deleting records with keys: {:?}", keys));
+    Some((name.to_string(), format, compression))
+}
 
-        self.records
-            .retain(|record| !keys.contains(&record.key.as_str()));
+/// Gets the extensions of a table file after its `stem`, like `json.gz`.
+fn file_extensions(file_path: &Path, stem: &str) -> String {
+    let file_name = file_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
 
-        match self.save() {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                return Err(err);
-            }
-        }
-    }
+    file_name
+        .strip_prefix(&format!("{}.", stem))
+        .unwrap_or_default()
+        .to_string()
+}
 
-    /// Deletes all the records from the table.
-    ///
-    /// Returns a `MooResult` with the result of the action.
-    pub fn delete_all(&mut self) -> MooResult<()> {
-        self.debugger.log(format!("Deleting all records"));
+/// Gets the modified time and length of a file, used to notice when someone else changed it.
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
 
-        self.records.clear();
+    Some((metadata.modified().ok()?, metadata.len()))
+}
 
-        match self.save() {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                return Err(err);
-            }
-        }
-    }
+/// The serialized records of a table, ready to be written to its file outside the table.
+#[cfg(feature = "tokio")]
+pub(crate) struct PendingWrite {
+    /// The bytes to write to the table file.
+    pub serialized_records: Vec<u8>,
+    /// The temporary file the bytes are written to first.
+    pub temp_path: PathBuf,
+    /// The table file the temporary file is renamed over.
+    pub file_path: PathBuf,
 }
 
-#[cfg(test)]
-mod tests {
-    use std::vec;
+/// The operation counters of a table, shared by every handle of the table.
+///
+/// They are atomic so reads through `&self` can count themselves without a lock.
+#[derive(Debug, Default)]
+struct Metrics {
+    gets: AtomicU64,
+    inserts: AtomicU64,
+    updates: AtomicU64,
+    deletes: AtomicU64,
+    saves: AtomicU64,
+    save_nanos: AtomicU64,
+}
 
-    use super::MooClient;
-    use crate::{Configuration, MooRecord};
+/// The signature of a closure checking a record, returning an error message to reject it.
+type ValidateFn<K, T> = dyn Fn(&K, &T) -> Result<(), String> + Send + Sync;
 
-    #[test]
-    fn test_delete_many() {
-        let mut db = MooClient::<String>::new(
-            "test_delete_many",
-            None,
-            Some(Configuration {
-                db_dir: "db/moo",
-                debug_mode: true,
-                debug_level: None,
-            }),
-        )
-        .unwrap();
+/// A closure checking a record before it's written to a table, see `MooTable::set_validator`.
+#[derive(Clone)]
+struct Validator<K, T>(Arc<ValidateFn<K, T>>);
 
-        db.reset_table().unwrap();
+impl<K, T> Debug for Validator<K, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Validator")
+    }
+}
 
-        let mut people = db.get_table().unwrap();
+/// The channels of everyone subscribed to changes on a table, shared by every handle of the table.
+type Subscribers<T, K> = Arc<Mutex<Vec<Sender<ChangeEvent<T, K>>>>>;
 
-        for i in 0..50 {
-            let data = format!("Example Person {}", i);
+/// The database table containing records.
+///
+/// Records are stored under keys of type `K` and hold values of type `T`.
+#[derive(Debug, Clone)]
+pub struct MooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    pub name: String,
+    /// The table file, `None` for tables with `StorageTypes::Memory`.
+    pub file: Option<Arc<Mutex<File>>>,
+    pub records: MooRecords<T, K>,
+    pub config: Configuration,
+    pub debugger: DebugClient,
+    /// The channels of everyone subscribed to changes on this table.
+    subscribers: Subscribers<T, K>,
+    /// The counters behind `metrics`.
+    metrics: Arc<Metrics>,
+    /// Checks every inserted or updated record, when set.
+    validator: Option<Validator<K, T>>,
+    /// The position of every record in `records`, by key.
+    key_index: HashMap<K, usize>,
+    /// The numeric range indexes on this table, by name.
+    numeric_indexes: HashMap<String, NumericIndex<K, T>>,
+    /// Whether the in-memory records have changes that aren't saved to disk.
+    dirty: bool,
+    /// The keys changed in memory since the table was last loaded or saved.
+    touched: HashSet<K>,
+    /// The keys of the records removed by `clear_memory`, deleted from the table file by the next save.
+    cleared: HashSet<K>,
+    /// The path to the table file.
+    file_path: PathBuf,
+    /// The format the table file is serialized in.
+    format: SerializationFormat,
+    /// The compression of the table file, if any.
+    compression: Option<Compression>,
+    /// The modified time and length of the table file when it was last loaded or saved.
+    stamp: Option<(SystemTime, u64)>,
+    /// The audit log every saved change is mirrored to, when enabled.
+    audit: Option<AuditLog>,
+    /// The write-ahead log changes are appended to instead of rewriting the table file, when enabled.
+    wal: Option<WriteAheadLog>,
+    /// The locked lock file of the table, when enabled. The lock is released once the last handle is dropped.
+    lock: Option<Arc<File>>,
+}
 
-            people.insert(&i.to_string(), data).unwrap();
+/// A table whose records are keyed by `String`, like every table before key types were configurable.
+pub type MooStringTable<T> = MooTable<String, T>;
+
+/// A table shared between threads.
+///
+/// Any number of threads can read the table at once through `read`, while `write` gives one thread
+/// exclusive access to change it. Create one with `MooTable::into_shared`.
+///
+/// This is where tables get their concurrency. The table file itself sits behind a mutex, since reading
+/// it also reopens its handle.
+pub type SharedMooTable<K, T> = Arc<RwLock<MooTable<K, T>>>;
+
+impl<K, T> MooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Creates a new table for a database instance.
+    ///
+    /// The `name` of the table to create.
+    ///
+    /// The `path` to the directory where the table will be stored.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn new(
+        name: &str,
+        path: &Path,
+        config: Configuration,
+        debugger: DebugClient,
+    ) -> MooResult<MooTable<K, T>> {
+        if let Some(ratio) = config
+            .auto_compact_ratio
+            .filter(|ratio| ratio.is_nan() || *ratio <= 1.0)
+        {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: format!("Auto compact ratio must be greater than 1, got: {}", ratio),
+            });
         }
 
-        assert_eq!(people.records.len(), 50);
+        // An existing table file keeps its format and compression, new tables use the configured ones.
+        let (file_path, format, compression) = match find_table_file(path, name, &config) {
+            Some(existing) => existing,
+            None => (
+                table_file_path(path, name, &config, config.format, config.compression),
+                config.format,
+                config.compression,
+            ),
+        };
 
-        people.delete_many(vec!["1", "2", "3"]).unwrap();
+        if config.storage == StorageTypes::Memory {
+            return Ok(Self {
+                name: name.to_string(),
+                file: None,
+                records: Vec::new(),
+                config,
+                debugger,
+                subscribers: Arc::new(Mutex::new(Vec::new())),
+                metrics: Arc::new(Metrics::default()),
+                validator: None,
+                key_index: HashMap::new(),
+                numeric_indexes: HashMap::new(),
+                dirty: false,
+                touched: HashSet::new(),
+                cleared: HashSet::new(),
+                file_path,
+                format,
+                compression,
+                stamp: None,
+                audit: None,
+                wal: None,
+                lock: None,
+            });
+        }
 
-        assert_eq!(people.records.len(), 47);
+        let stem = file_stem(&config, name);
 
-        let u = vec![
-            MooRecord {
-                key: "4".to_string(),
-                value: "Example Person 4 updated".to_string(),
-            },
+        let lock = if config.lock_tables {
+            Some(Arc::new(lock_table(
+                &path.join(format!("{}.lock", stem)),
+                name,
+            )?))
+        } else {
+            None
+        };
+
+        let mut file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&file_path) {
+            Ok(file) => file,
+            Err(_) => {
+                match File::create(&file_path) {
+                    Ok(file) => file,
+                    Err(_) => {
+                        return Err(MooError {
+                            code: MooErrorCodes::Fatal,
+                            message: "Failed to create table file. Might be missing permissions to write the directory?".to_string()
+                        })
+                    }
+                }
+            }
+        };
+
+        let wal = if config.wal {
+            Some(WriteAheadLog::new(&path.join(format!("{}.wal", stem))))
+        } else {
+            None
+        };
+
+        let mut records = Self::read_records(&mut file, format, compression, &config, &debugger)?;
+
+        if let Some(wal) = &wal {
+            records = wal.replay(records)?;
+        }
+
+        let stamp = file_stamp(&file_path);
+
+        let audit = if config.audit_log {
+            Some(AuditLog::new(&path.join(format!("{}.audit.jsonl", stem)))?)
+        } else {
+            None
+        };
+
+        let mut table = Self {
+            name: name.to_string(),
+            file: Some(Arc::new(Mutex::new(file))),
+            records,
+            config,
+            debugger,
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            metrics: Arc::new(Metrics::default()),
+            validator: None,
+            key_index: HashMap::new(),
+            numeric_indexes: HashMap::new(),
+            dirty: false,
+            touched: HashSet::new(),
+            cleared: HashSet::new(),
+            file_path,
+            format,
+            compression,
+            stamp,
+            audit,
+            wal,
+            lock,
+        };
+
+        table.rebuild_indexes();
+
+        Ok(table)
+    }
+
+    /// Reads and parses all the records from the start of the table file.
+    ///
+    /// Records in a file with an older schema version than the `config` are upgraded by its `migrate` function.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn read_records(
+        file: &mut File,
+        format: SerializationFormat,
+        compression: Option<Compression>,
+        config: &Configuration,
+        debugger: &DebugClient,
+    ) -> MooResult<MooRecords<T, K>> {
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut contents = Vec::new();
+
+        file.read_to_end(&mut contents)?;
+
+        if contents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match format::strip_checksum(&contents) {
+            Ok(Some(bytes)) => contents = bytes.to_vec(),
+            Ok(None) => debugger.log(
+                DebugLevel::Warning,
+                "Table file has no checksum, it will get one the next time it is saved.",
+            ),
+            Err(_) if config.recover_corrupt => {
+                debugger.log(
+                    DebugLevel::Warning,
+                    "Table file doesn't match its checksum, trying to recover its records.",
+                );
+
+                contents = format::skip_checksum(&contents).to_vec();
+            }
+            Err(err) => return Err(err),
+        }
+
+        let (version, bytes) = format::strip_schema_version(&contents)?;
+        let mut contents = bytes.to_vec();
+
+        if let Some(key) = &config.encryption_key {
+            contents = format::decrypt(key, &contents)?;
+        }
+
+        if let Some(compression) = compression {
+            contents = compression.decompress(&contents)?;
+        }
+
+        let migrate = match config.migrate {
+            Some(migrate) if version < config.schema_version => Some(migrate),
+            _ => None,
+        };
+
+        let recover = config.recover_corrupt && format == SerializationFormat::Json;
+
+        if migrate.is_none() {
+            match format.deserialize(&contents) {
+                Ok(records) => return Ok(records),
+                Err(err) if !recover => return Err(err),
+                Err(_) => {}
+            }
+        }
+
+        let mut records: serde_json::Value = match format.deserialize(&contents) {
+            Ok(records) => records,
+            Err(err) if !recover => return Err(err),
+            Err(_) => {
+                let (records, dropped) = format::recover_json_records(&contents)?;
+
+                debugger.log(
+                    DebugLevel::Warning,
+                    format!(
+                        "Table file is damaged, recovered {} records and dropped the last {} bytes.",
+                        records.len(),
+                        dropped
+                    ),
+                );
+
+                serde_json::Value::Array(records)
+            }
+        };
+
+        if let Some(migrate) = migrate {
+            if let Some(records) = records.as_array_mut() {
+                for record in records {
+                    if let Some(value) = record.get_mut("value") {
+                        *value = migrate(version, value.take());
+                    }
+                }
+            }
+
+            debugger.log(
+                DebugLevel::Info,
+                format!(
+                    "Migrated table file from schema version {} to {}",
+                    version, config.schema_version
+                ),
+            );
+        }
+
+        Ok(serde_json::from_value(records)?)
+    }
+
+    /// Reads the records currently in the table file, with the write-ahead log replayed on top.
+    ///
+    /// The file handle is reopened first, since another table may have replaced the table file after it was opened.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn read_file(&self) -> MooResult<MooRecords<T, K>> {
+        let mut file = self.lock_file()?;
+
+        *file = reopen_file(&self.file_path)?;
+
+        let records = Self::read_records(
+            &mut file,
+            self.format,
+            self.compression,
+            &self.config,
+            &self.debugger,
+        )?;
+
+        match &self.wal {
+            Some(wal) => wal.replay(records),
+            None => Ok(records),
+        }
+    }
+
+    /// Replaces the in-memory records with the ones currently in the table file.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn reload(&mut self) -> MooResult<()> {
+        if self.is_memory() {
+            return Ok(());
+        }
+
+        let records = self.read_file()?;
+
+        self.records = records;
+        self.dirty = false;
+        self.touched.clear();
+        self.cleared.clear();
+        self.stamp = file_stamp(&self.file_path);
+
+        self.rebuild_indexes();
+
+        Ok(())
+    }
+
+    /// Clears all records and truncates the table file.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn reset(&mut self) -> MooResult<()> {
+        let events = self
+            .records
+            .drain(..)
+            .map(|record| record.key)
+            .chain(self.cleared.drain())
+            .map(|key| ChangeEvent::Deleted { key })
+            .collect();
+
+        self.clear_indexes();
+
+        self.dirty = false;
+        self.touched.clear();
+
+        if self.is_memory() {
+            return self.notify(events);
+        }
+
+        self.write_file(&[])?;
+        self.notify(events)
+    }
+
+    /// Deletes this table from the database instance.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn delete_self(&mut self) -> MooResult<()> {
+        let events = self
+            .records
+            .drain(..)
+            .map(|record| record.key)
+            .chain(self.cleared.drain())
+            .map(|key| ChangeEvent::Deleted { key })
+            .collect();
+
+        self.clear_indexes();
+
+        self.dirty = false;
+        self.touched.clear();
+
+        if self.is_memory() {
+            return self.notify(events);
+        }
+
+        if let Some(wal) = &self.wal {
+            wal.truncate()?;
+        }
+
+        if self.lock.take().is_some() {
+            let lock_name = format!("{}.lock", file_stem(&self.config, &self.name));
+            let _ = fs::remove_file(self.file_path.with_file_name(lock_name));
+        }
+
+        match fs::remove_file(&self.file_path) {
+            Ok(_) => self.notify(events),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: format!("Failed to delete table file: {}. Might be missing permissions to delete the file.", self.name),
+            })
+        }
+    }
+
+    /// Gets the value of a record that hasn't expired, without removing the record if it has.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    pub(crate) fn live_value<Q>(&self, key: &Q) -> MooResult<&T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        match self.key_index.get(key) {
+            Some(&index) if self.records[index].is_live() => Ok(&self.records[index].value),
+            _ => Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No record found with key: {:?}", key),
+            }),
+        }
+    }
+
+    /// Whether this table only lives in memory and never touches disk.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn is_memory(&self) -> bool {
+        self.config.storage == StorageTypes::Memory
+    }
+
+    /// Locks the table file for reading or writing.
+    ///
+    /// Reading the file also reopens its handle, so every access is exclusive. Share the table with
+    /// `into_shared` for concurrent reads of the records.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn lock_file(&self) -> MooResult<MutexGuard<'_, File>> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => {
+                return Err(MooError {
+                    code: MooErrorCodes::Error,
+                    message: format!(
+                        "Table: {} is stored in memory and has no table file.",
+                        self.name
+                    ),
+                })
+            }
+        };
+
+        match file.lock() {
+            Ok(file) => Ok(file),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: "Failed to lock table file.".to_string(),
+            }),
+        }
+    }
+
+    /// Adds a record to every numeric index on this table.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn index_record(&mut self, key: &K, value: &T) {
+        for index in self.numeric_indexes.values_mut() {
+            index.add(key, value);
+        }
+    }
+
+    /// Removes a record from every numeric index on this table.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn unindex_record(&mut self, key: &K, value: &T) {
+        for index in self.numeric_indexes.values_mut() {
+            index.remove(key, value);
+        }
+    }
+
+    /// Removes every record from the key index and the numeric indexes on this table.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn clear_indexes(&mut self) {
+        self.key_index.clear();
+
+        for index in self.numeric_indexes.values_mut() {
+            index.clear();
+        }
+    }
+
+    /// Rebuilds the key index and every numeric index on this table from the current records.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn rebuild_indexes(&mut self) {
+        self.clear_indexes();
+        self.reindex_keys_from(0);
+
+        for record in self.records.iter().filter(|record| !record.deleted) {
+            for index in self.numeric_indexes.values_mut() {
+                index.add(&record.key, &record.value);
+            }
+        }
+    }
+
+    /// Updates the key index for every record from the `start` position onward,
+    /// after records were added or removed there.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn reindex_keys_from(&mut self, start: usize) {
+        for (index, record) in self.records.iter().enumerate().skip(start) {
+            self.key_index.insert(record.key.clone(), index);
+        }
+    }
+
+    /// Removes the record at the `index` from memory and from every index, without saving.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn remove_record(&mut self, index: usize) -> MooRecord<T, K> {
+        let record = self.records.remove(index);
+
+        self.key_index.remove(&record.key);
+        self.reindex_keys_from(index);
+        self.unindex_record(&record.key, &record.value);
+        self.mark_changed(&record.key);
+
+        record
+    }
+
+    /// Deletes the record at the `index` from memory and from every index, without saving.
+    ///
+    /// With `soft_delete` enabled in the config the record stays in the table, only marked as deleted.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn delete_record(&mut self, index: usize) -> MooRecord<T, K> {
+        if !self.config.soft_delete {
+            return self.remove_record(index);
+        }
+
+        self.records[index].deleted = true;
+
+        let record = self.records[index].clone();
+
+        self.unindex_record(&record.key, &record.value);
+        self.mark_changed(&record.key);
+
+        record
+    }
+
+    /// Captures the in-memory state of the table, so a change can be undone if it can't be saved.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn save_state(&self) -> TableState<T, K> {
+        TableState {
+            records: self.records.clone(),
+            touched: self.touched.clone(),
+            dirty: self.dirty,
+        }
+    }
+
+    /// Restores the in-memory state of the table from a saved state and rebuilds its indexes.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn restore_state(&mut self, state: TableState<T, K>) {
+        self.records = state.records;
+        self.rollback(state.touched, state.dirty);
+    }
+
+    /// Restores the change tracking of the table after its records were rolled back and rebuilds its indexes.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn rollback(&mut self, touched: HashSet<K>, dirty: bool) {
+        self.touched = touched;
+        self.dirty = dirty;
+
+        self.rebuild_indexes();
+    }
+
+    /// Marks a record as changed in memory but not saved yet.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn mark_changed(&mut self, key: &K) {
+        self.dirty = true;
+        self.touched.insert(key.clone());
+    }
+
+    /// Writes saved change events to the audit log and sends them to every subscriber of this table.
+    ///
+    /// Subscribers whose receiver has been dropped are removed.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn notify(&self, events: Vec<ChangeEvent<T, K>>) -> MooResult<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        for event in &events {
+            let counter = match event {
+                ChangeEvent::Inserted { .. } => &self.metrics.inserts,
+                ChangeEvent::Updated { .. } => &self.metrics.updates,
+                ChangeEvent::Deleted { .. } => &self.metrics.deletes,
+            };
+
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if let Some(audit) = &self.audit {
+            audit.record(&events)?;
+        }
+
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|subscriber| {
+                events
+                    .iter()
+                    .all(|event| subscriber.send(event.clone()).is_ok())
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Subscribe to every change made to this table.
+    ///
+    /// Each subscriber gets its own receiver and receives every insert, update and delete after it has been saved.
+    /// With autosave disabled, changes are sent as soon as they are made instead of when they are flushed.
+    /// Dropping the receiver unsubscribes it, and writes keep working for every other subscriber.
+    ///
+    /// Returns a `Receiver` of the change events.
+    pub fn subscribe(&self) -> Receiver<ChangeEvent<T, K>> {
+        let (sender, receiver) = channel();
+
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
+        }
+
+        receiver
+    }
+
+    /// Get the stats of this table.
+    ///
+    /// The record count is taken from memory, the size and modified time from the table file.
+    /// Memory tables have no file, so their size is `0` and they have no modified time.
+    ///
+    /// Returns a `MooResult` with the stats of the table.
+    pub fn stats(&self) -> MooResult<TableStats> {
+        if self.is_memory() {
+            return Ok(TableStats {
+                records: self.records.len(),
+                size: 0,
+                modified: None,
+            });
+        }
+
+        let metadata = match fs::metadata(&self.file_path) {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                return Err(MooError {
+                    code: MooErrorCodes::Fatal,
+                    message: format!("Failed to read metadata of table file: {}", self.name),
+                })
+            }
+        };
+
+        Ok(TableStats {
+            records: self.records.len(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Get the size of the table file in bytes.
+    ///
+    /// Memory tables have no file, so the size their records would take up when serialized is returned instead.
+    ///
+    /// Returns a `MooResult` with the size in bytes.
+    pub fn size_on_disk(&self) -> MooResult<u64> {
+        if self.is_memory() {
+            return Ok(self.serialize_records()?.len() as u64);
+        }
+
+        match fs::metadata(&self.file_path) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: format!("Failed to read metadata of table file: {}", self.name),
+            }),
+        }
+    }
+
+    /// Get the operation counts of this table and the time spent saving it, since it was opened.
+    ///
+    /// The counters are shared by every handle of the table, like the ones returned by `get_table`.
+    /// Inserts, updates and deletes are counted per record, gets per lookup.
+    pub fn metrics(&self) -> TableMetrics {
+        TableMetrics {
+            gets: self.metrics.gets.load(Ordering::Relaxed),
+            inserts: self.metrics.inserts.load(Ordering::Relaxed),
+            updates: self.metrics.updates.load(Ordering::Relaxed),
+            deletes: self.metrics.deletes.load(Ordering::Relaxed),
+            saves: self.metrics.saves.load(Ordering::Relaxed),
+            save_duration: Duration::from_nanos(self.metrics.save_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Counts a save to disk that took `duration`.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    pub(crate) fn record_save(&self, duration: Duration) {
+        self.metrics.saves.fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .save_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Counts a lookup of a record by key.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    pub(crate) fn record_get(&self) {
+        self.metrics.gets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Set a validator that checks every record before it's inserted or updated.
+    ///
+    /// The `validator` gets the key and value of the record, and returns an error message to reject it.
+    /// It runs on `insert`, `update`, `upsert`, their bulk variants and batches, but not on changes made in place
+    /// with `modify` or `entry`. A rejected record fails with `MooErrorCodes::Warn` and nothing is changed or saved.
+    ///
+    /// Only this handle of the table uses the validator, it replaces any validator set before.
+    pub fn set_validator<F>(&mut self, validator: F)
+    where
+        F: Fn(&K, &T) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validator = Some(Validator(Arc::new(validator)));
+    }
+
+    /// Remove the validator set with `set_validator`.
+    pub fn clear_validator(&mut self) {
+        self.validator = None;
+    }
+
+    /// Checks a record with the validator of the table, if it has one.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn validate(&self, key: &K, value: &T) -> MooResult<()> {
+        let validator = match &self.validator {
+            Some(validator) => validator,
+            None => return Ok(()),
+        };
+
+        match (validator.0)(key, value) {
+            Ok(_) => Ok(()),
+            Err(message) => {
+                self.debugger.log(
+                    DebugLevel::Warning,
+                    format!("Record with key: {:?} failed validation: {}", key, message),
+                );
+
+                Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message,
+                })
+            }
+        }
+    }
+
+    /// Whether the table has in-memory changes that haven't been saved to disk yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Throw away all in-memory changes that haven't been saved to disk.
+    ///
+    /// The records are reloaded from the table file, so the table matches what was last saved.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn discard_changes(&mut self) -> MooResult<()> {
+        self.reload()?;
+
+        self.debugger.log(
+            DebugLevel::Warning,
+            format!("Discarded unsaved changes in table: {}", self.name),
+        );
+
+        Ok(())
+    }
+
+    /// Read the table file again and replace the in-memory records with its records.
+    ///
+    /// Use this to pick up changes another process or handle made to the table file, like restoring a backup.
+    /// Unsaved changes in memory are lost. Memory tables have no file, so nothing happens.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn reload_from_disk(&mut self) -> MooResult<()> {
+        self.reload()?;
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!(
+                "Reloaded {} records from table file: {}",
+                self.records.len(),
+                self.name
+            ),
+        );
+
+        Ok(())
+    }
+
+    /// Remove all records from memory without touching the table file.
+    ///
+    /// The table file keeps the old records until the table is saved again, which makes it possible to
+    /// repopulate the table and replace the file in one go. With autosave enabled the next change saves the table,
+    /// so turn it off or repopulate with a single call like `insert_many` to keep the old file until the new
+    /// records are ready. Use `reload_from_disk` to get the records back from the file instead.
+    ///
+    /// Clearing alone doesn't make the table dirty, so dropping it keeps the file as it is. The save that follows
+    /// deletes the cleared records that weren't put back from the file and sends their delete events.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn clear_memory(&mut self) -> MooResult<()> {
+        let count = self.records.len();
+
+        self.cleared
+            .extend(self.records.drain(..).map(|record| record.key));
+
+        self.clear_indexes();
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Cleared {} records from memory", count),
+        );
+
+        Ok(())
+    }
+
+    /// Marks the records removed by `clear_memory` that weren't put back as changed, so the next save deletes them.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn mark_cleared(&mut self) {
+        let keys: Vec<K> = self
+            .cleared
+            .iter()
+            .filter(|key| !self.key_index.contains_key(*key))
+            .cloned()
+            .collect();
+
+        for key in &keys {
+            self.mark_changed(key);
+        }
+    }
+
+    /// Sends a delete event for every record removed by `clear_memory` once a save deleted it from the table file.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn notify_cleared(&mut self) -> MooResult<()> {
+        let events = self
+            .cleared
+            .drain()
+            .filter(|key| !self.key_index.contains_key(key))
+            .map(|key| ChangeEvent::Deleted { key })
+            .collect();
+
+        self.notify(events)
+    }
+
+    /// Serializes the table records into the bytes written to disk.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn serialize_records(&self) -> MooResult<Vec<u8>> {
+        self.encode_records(&self.records, self.format, self.compression)
+    }
+
+    /// Serializes `records` into the bytes of a table file in the `format` and `compression`,
+    /// encrypted and checksummed like the table file.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn encode_records<R>(
+        &self,
+        records: &R,
+        format: SerializationFormat,
+        compression: Option<Compression>,
+    ) -> MooResult<Vec<u8>>
+    where
+        R: Serialize + ?Sized,
+    {
+        let mut serialized_records =
+            if self.config.pretty_json && format == SerializationFormat::Json {
+                serde_json::to_vec_pretty(records)?
+            } else {
+                format.serialize(records)?
+            };
+
+        if let Some(compression) = compression {
+            serialized_records = compression.compress(&serialized_records)?;
+        }
+
+        if let Some(key) = &self.config.encryption_key {
+            serialized_records = format::encrypt(key, &serialized_records)?;
+        }
+
+        serialized_records =
+            format::add_schema_version(serialized_records, self.config.schema_version);
+
+        Ok(format::add_checksum(serialized_records))
+    }
+
+    /// Write a copy of the table to another file.
+    ///
+    /// The `path` of the file to write the copy to. It is created or overwritten.
+    ///
+    /// The `format` and `compression` to write the copy in. It is encrypted with the configured key, if any,
+    /// so a table with the same configuration can open it.
+    ///
+    /// Expired and deleted records are left out of the copy. The copy is written to a temporary file first,
+    /// which is then renamed over the `path`, and the live table file is not affected.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn save_as(
+        &self,
+        path: &Path,
+        format: SerializationFormat,
+        compression: Option<Compression>,
+    ) -> MooResult<()> {
+        let records: Vec<&MooRecord<T, K>> = self
+            .records
+            .iter()
+            .filter(|record| record.is_live())
+            .collect();
+
+        let serialized_records = self.encode_records(&records, format, compression)?;
+
+        write_atomic(path, &serialized_records)
+    }
+
+    /// Checks if the table file was changed by someone else since this table last loaded or saved it,
+    /// and applies the configured `ConflictPolicy` before saving.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn resolve_conflicts(&mut self) -> MooResult<()> {
+        if self.config.conflict_policy == ConflictPolicy::Overwrite || self.is_memory() {
+            return Ok(());
+        }
+
+        if file_stamp(&self.file_path) == self.stamp {
+            return Ok(());
+        }
+
+        if self.config.conflict_policy == ConflictPolicy::Abort {
+            return Err(MooError {
+                code: MooErrorCodes::Error,
+                message: format!(
+                    "Table file: {} was changed by another handle since it was loaded.",
+                    self.name
+                ),
+            });
+        }
+
+        let disk_records = self.read_file()?;
+
+        let mut ours: HashMap<K, MooRecord<T, K>> = self
+            .records
+            .drain(..)
+            .filter(|record| self.touched.contains(&record.key))
+            .map(|record| (record.key.clone(), record))
+            .collect();
+
+        for record in disk_records {
+            if !self.touched.contains(&record.key) {
+                self.records.push(record);
+            } else if let Some(ours) = ours.remove(&record.key) {
+                self.records.push(ours);
+            }
+        }
+
+        // Whatever is left was inserted by this table and isn't on disk yet.
+        self.records.extend(ours.into_values());
+
+        self.rebuild_indexes();
+
+        self.debugger.log(
+            DebugLevel::Warning,
+            format!("Merged external changes into table: {}", self.name),
+        );
+
+        Ok(())
+    }
+
+    /// Saves the table to disk after an action, unless autosave is disabled in the config.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn save(&mut self) -> MooResult<()> {
+        if !self.config.autosave {
+            return Ok(());
+        }
+
+        self.flush()
+    }
+
+    /// Write all in-memory changes to disk.
+    ///
+    /// Tables save after every change by default, so this is only needed when `autosave` is disabled in the config.
+    /// Use `is_dirty` to check if there are changes that haven't been flushed yet.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn flush(&mut self) -> MooResult<()> {
+        if self.is_memory() {
+            self.dirty = false;
+            self.touched.clear();
+            return self.notify_cleared();
+        }
+
+        let started = Instant::now();
+
+        self.write_changes()?;
+        self.record_save(started.elapsed());
+
+        Ok(())
+    }
+
+    /// Writes the in-memory changes to the write-ahead log or the table file.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn write_changes(&mut self) -> MooResult<()> {
+        self.mark_cleared();
+        self.resolve_conflicts()?;
+
+        if self.wal.is_some() {
+            return self.append_wal();
+        }
+
+        let serialized_records = self.serialize_records()?;
+
+        self.write_file(&serialized_records)
+    }
+
+    /// Appends every record changed since the last save to the write-ahead log.
+    ///
+    /// The log is folded into the table file once the files on disk are more than `auto_compact_ratio` times
+    /// the size of the table file.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn append_wal(&mut self) -> MooResult<()> {
+        let wal = match &self.wal {
+            Some(wal) => wal,
+            None => return Ok(()),
+        };
+
+        let entries: Vec<WalEntry<&T, &K>> = self
+            .touched
+            .iter()
+            .map(|key| match self.key_index.get(key) {
+                Some(&index) => WalEntry::Put {
+                    key,
+                    value: &self.records[index].value,
+                    expires_at: self.records[index].expires_at,
+                    deleted: self.records[index].deleted,
+                },
+                None => WalEntry::Delete { key },
+            })
+            .collect();
+
+        if !entries.is_empty() {
+            wal.append(&entries)?;
+        }
+
+        self.dirty = false;
+        self.touched.clear();
+        self.notify_cleared()?;
+
+        if let Some(ratio) = self.config.auto_compact_ratio {
+            let file_size = fs::metadata(&self.file_path).map_or(0, |metadata| metadata.len());
+
+            if self.disk_size() as f64 > file_size as f64 * ratio as f64 {
+                self.compact()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Export all records of the table as JSON to a writer.
+    ///
+    /// The `writer` to write the records to, like a file, a socket or a compressed stream.
+    ///
+    /// The records can be read back into a table with `import`. Soft deleted records are skipped,
+    /// expired records that haven't been removed yet are kept with their expiry.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn export<W: Write>(&self, writer: W) -> MooResult<()> {
+        serde_json::to_writer(writer, &self.all())?;
+
+        Ok(())
+    }
+
+    /// Import records written by `export` from a reader.
+    ///
+    /// The `reader` to read the JSON records from.
+    ///
+    /// The `mode` deciding whether the imported records replace the table or are merged into it.
+    /// When merging, an imported record overwrites the record with the same key.
+    /// Imported records keep their expiry, and records marked as deleted are imported as soft deleted records.
+    ///
+    /// The table is saved once for all records and left unchanged if the records can't be read or saved.
+    ///
+    /// Returns a `MooResult` with the number of imported records.
+    pub fn import<R: Read>(&mut self, reader: R, mode: ImportMode) -> MooResult<usize> {
+        let imported: MooRecords<T, K> = serde_json::from_reader(reader)?;
+        let count = imported.len();
+        let state = self.save_state();
+        let mut events = Vec::new();
+
+        if mode == ImportMode::Replace {
+            events.extend(
+                self.records
+                    .drain(..)
+                    .map(|record| ChangeEvent::Deleted { key: record.key }),
+            );
+
+            for event in &events {
+                if let ChangeEvent::Deleted { key } = event {
+                    self.mark_changed(key);
+                }
+            }
+
+            self.clear_indexes();
+        }
+
+        for record in imported {
+            events.extend(self.put_stored_record(record));
+        }
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger
+            .log(DebugLevel::Info, format!("Imported {} records", count));
+
+        self.notify(events)?;
+
+        Ok(count)
+    }
+
+    /// Import records from a JSON object mapping keys to values, like `{"1": {...}, "2": {...}}`.
+    ///
+    /// The `reader` to read the JSON object from. This is for data that wasn't written by `export`.
+    ///
+    /// Every entry is inserted like with `insert_many`, so nothing is imported if a key is already in the table.
+    /// Keys that aren't strings, like numbers, are parsed from the object keys.
+    ///
+    /// Returns a `MooResult` with the number of imported records.
+    pub fn import_json_map<R: Read>(&mut self, reader: R) -> MooResult<usize> {
+        let map: serde_json::Map<String, serde_json::Value> = serde_json::from_reader(reader)?;
+
+        if map.is_empty() {
+            return Ok(0);
+        }
+
+        let mut records = Vec::with_capacity(map.len());
+
+        for (key, value) in map {
+            let key = match serde_json::from_value(serde_json::Value::String(key.clone())) {
+                Ok(key) => key,
+                Err(_) => serde_json::from_str(&key)?,
+            };
+
+            records.push(MooRecord {
+                key,
+                value: serde_json::from_value(value)?,
+                expires_at: None,
+                deleted: false,
+            });
+        }
+
+        let count = records.len();
+
+        self.insert_many(records)?;
+
+        Ok(count)
+    }
+
+    /// Export the records of the table as CSV to a writer, to open them in a spreadsheet.
+    ///
+    /// The `writer` to write the rows to.
+    ///
+    /// The first row is a header with `key` and the field names of the values in alphabetical order.
+    /// Every record is a row with its key in the first column. Nested fields are written as JSON into a single cell,
+    /// and a value that isn't a struct or map is written to a single `value` column.
+    /// Expired and deleted records are skipped.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn export_csv<W: Write>(&self, mut writer: W) -> MooResult<()> {
+        let mut rows = Vec::new();
+        let mut columns = BTreeSet::new();
+
+        for record in self.records.iter().filter(|record| record.is_live()) {
+            let fields = match serde_json::to_value(&record.value)? {
+                serde_json::Value::Object(fields) => fields,
+                value => serde_json::Map::from_iter([("value".to_string(), value)]),
+            };
+
+            columns.extend(fields.keys().cloned());
+
+            rows.push((serde_json::to_value(&record.key)?, fields));
+        }
+
+        let header: Vec<String> = std::iter::once("key".to_string())
+            .chain(columns.iter().cloned())
+            .map(|column| csv_cell(&serde_json::Value::String(column)))
+            .collect();
+
+        writeln!(writer, "{}", header.join(","))?;
+
+        for (key, fields) in rows {
+            let row: Vec<String> = std::iter::once(csv_cell(&key))
+                .chain(
+                    columns
+                        .iter()
+                        .map(|column| fields.get(column).map_or_else(String::new, csv_cell)),
+                )
+                .collect();
+
+            writeln!(writer, "{}", row.join(","))?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Dump all records of the table to a JSON string, in the same shape as `export`. Soft deleted records are skipped.
+    ///
+    /// The `pretty` flag to indent the JSON with `serde_json::to_string_pretty`, which is easier to read in fixtures.
+    ///
+    /// Returns a `MooResult` with the JSON string.
+    pub fn to_json_string(&self, pretty: bool) -> MooResult<String> {
+        let json = if pretty {
+            serde_json::to_string_pretty(&self.all())?
+        } else {
+            serde_json::to_string(&self.all())?
+        };
+
+        Ok(json)
+    }
+
+    /// Load records from a JSON string written by `to_json_string` or `export`.
+    ///
+    /// The `json` string to read the records from.
+    ///
+    /// The `mode` deciding whether the loaded records replace the table or are merged into it, like with `import`.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn from_json_string(&mut self, json: &str, mode: ImportMode) -> MooResult<()> {
+        self.import(json.as_bytes(), mode)?;
+
+        Ok(())
+    }
+
+    /// Rewrites the table file with the serialized records.
+    ///
+    /// The records are written to a temporary file next to the table file first, which is then renamed over it.
+    /// The rename is atomic, so a crash in the middle of a save leaves the previous table file intact.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn write_file(&mut self, serialized_records: &[u8]) -> MooResult<()> {
+        let file = self.lock_file()?;
+
+        write_atomic(&self.file_path, serialized_records)?;
+
+        drop(file);
+
+        self.finish_write()
+    }
+
+    /// Serializes the records for a write that is done outside the table, like by `AsyncMooTable`.
+    ///
+    /// Memory tables have nothing to write, so they are marked as saved and `None` is returned.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn prepare_write(&mut self) -> MooResult<Option<PendingWrite>> {
+        if self.is_memory() {
+            self.dirty = false;
+            self.touched.clear();
+            self.notify_cleared()?;
+            return Ok(None);
+        }
+
+        self.mark_cleared();
+        self.resolve_conflicts()?;
+
+        Ok(Some(PendingWrite {
+            serialized_records: self.serialize_records()?,
+            temp_path: temp_file_path(&self.file_path),
+            file_path: self.file_path.clone(),
+        }))
+    }
+
+    /// Updates the table after its file was replaced by a write.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    pub(crate) fn finish_write(&mut self) -> MooResult<()> {
+        let mut file = self.lock_file()?;
+
+        // The table file now holds every change, so the write-ahead log can start over.
+        if let Some(wal) = &self.wal {
+            wal.truncate()?;
+        }
+
+        // The old handle still points at the replaced file, every clone of this table shares the new one.
+        *file = reopen_file(&self.file_path)?;
+
+        drop(file);
+
+        self.dirty = false;
+        self.touched.clear();
+        self.stamp = file_stamp(&self.file_path);
+
+        self.notify_cleared()
+    }
+
+    /// The number of bytes the table takes up on disk.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn disk_size(&self) -> u64 {
+        if self.is_memory() {
+            return 0;
+        }
+
+        let wal_size = self.wal.as_ref().map_or(0, |wal| wal.size());
+
+        fs::metadata(&self.file_path).map_or(0, |metadata| metadata.len()) + wal_size
+    }
+
+    /// Compact the table file so it only contains the current records.
+    ///
+    /// The table file is rewritten from the in-memory records, dropping any stale data.
+    /// Tables with the write-ahead log and an `auto_compact_ratio` configured do this automatically after a save.
+    /// Memory tables have no file, so this does nothing for them.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn compact(&mut self) -> MooResult<()> {
+        if self.is_memory() {
+            return Ok(());
+        }
+
+        let serialized_records = self.serialize_records()?;
+
+        self.write_file(&serialized_records)?;
+
+        self.debugger
+            .log(DebugLevel::Info, format!("Compacted table: {}", self.name));
+
+        Ok(())
+    }
+
+    /// Compact the table file like `compact`, with the records sorted by key.
+    ///
+    /// Sorting gives a deterministic file for the same records, which is easier to diff or check into a repository.
+    /// The in-memory records are sorted as well, so the table order is the key order afterwards instead of insertion order.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn compact_sorted(&mut self) -> MooResult<()>
+    where
+        K: Ord,
+    {
+        self.records.sort_by(|a, b| a.key.cmp(&b.key));
+        self.key_index.clear();
+        self.reindex_keys_from(0);
+
+        self.compact()
+    }
+
+    /// Gets the path of the snapshot file with a label, next to the table file and with the same extensions.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn snapshot_path(&self, label: &str) -> MooResult<PathBuf> {
+        if self.is_memory() {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: "Memory tables have no table file to snapshot.".to_string(),
+            });
+        }
+
+        if label.is_empty() || label.contains(['/', '\\']) {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: format!("Snapshot label: {:?} is not a valid file name.", label),
+            });
+        }
+
+        let stem = file_stem(&self.config, &self.name);
+        let extensions = file_extensions(&self.file_path, &stem);
+
+        Ok(self.file_path.with_file_name(format!(
+            "{}{}{}.{}",
+            stem, SNAPSHOT_INFIX, label, extensions
+        )))
+    }
+
+    /// Take a point-in-time snapshot of the table.
+    ///
+    /// The `label` of the snapshot, used to restore or delete it later. A snapshot with the same label is replaced.
+    ///
+    /// The snapshot is written from the in-memory records to `<name>.snapshot.<label>.json` next to the table file,
+    /// with the format, compression and encryption of the table. The records can't change while the table is borrowed,
+    /// so the snapshot is always consistent and includes changes that weren't flushed yet.
+    ///
+    /// Returns a `MooResult` with the path of the snapshot file.
+    pub fn snapshot(&self, label: &str) -> MooResult<PathBuf> {
+        let snapshot_path = self.snapshot_path(label)?;
+        let temp_path = temp_file_path(&snapshot_path);
+
+        if fs::write(&temp_path, self.serialize_records()?).is_err() {
+            return Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: format!(
+                    "Failed to write snapshot: {}. Might be missing permissions to write the directory?",
+                    label
+                ),
+            });
+        }
+
+        fs::rename(&temp_path, &snapshot_path)?;
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Took snapshot: {} of table: {}", label, self.name),
+        );
+
+        Ok(snapshot_path)
+    }
+
+    /// Roll the table back to a snapshot taken with `snapshot`.
+    ///
+    /// The `label` of the snapshot to restore.
+    ///
+    /// Every record is replaced by the records in the snapshot and the table file is overwritten right away,
+    /// even when autosave is disabled. The table is left unchanged if the snapshot can't be read or saved.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn restore_snapshot(&mut self, label: &str) -> MooResult<()> {
+        let snapshot_path = self.snapshot_path(label)?;
+
+        let mut file = match File::open(&snapshot_path) {
+            Ok(file) => file,
+            Err(_) => {
+                return Err(MooError {
+                    code: MooErrorCodes::NotFound,
+                    message: format!("No snapshot found with label: {}", label),
+                })
+            }
+        };
+
+        let restored = Self::read_records(
+            &mut file,
+            self.format,
+            self.compression,
+            &self.config,
+            &self.debugger,
+        )?;
+
+        let state = self.save_state();
+
+        let mut events: Vec<ChangeEvent<T, K>> = self
+            .records
+            .drain(..)
+            .map(|record| ChangeEvent::Deleted { key: record.key })
+            .collect();
+
+        for event in &events {
+            if let ChangeEvent::Deleted { key } = event {
+                self.mark_changed(key);
+            }
+        }
+
+        self.clear_indexes();
+
+        for record in restored {
+            events.extend(self.put_stored_record(record));
+        }
+
+        let written = self
+            .serialize_records()
+            .and_then(|serialized_records| self.write_file(&serialized_records));
+
+        if let Err(err) = written {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Warning,
+            format!("Restored snapshot: {} of table: {}", label, self.name),
+        );
+
+        self.notify(events)
+    }
+
+    /// List the labels of every snapshot of the table, sorted by label.
+    ///
+    /// Memory tables have no snapshots, so the list is always empty for them.
+    ///
+    /// Returns a `MooResult` with the snapshot labels.
+    pub fn list_snapshots(&self) -> MooResult<Vec<String>> {
+        if self.is_memory() {
+            return Ok(Vec::new());
+        }
+
+        let dir = self.file_path.parent().unwrap_or(Path::new("."));
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                return Err(MooError {
+                    code: MooErrorCodes::Fatal,
+                    message: "Failed to read database directory.".to_string(),
+                })
+            }
+        };
+
+        let stem = file_stem(&self.config, &self.name);
+        let extensions = file_extensions(&self.file_path, &stem);
+        let prefix = format!("{}{}", stem, SNAPSHOT_INFIX);
+        let suffix = format!(".{}", extensions);
+
+        let mut labels: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+
+                file_name
+                    .strip_prefix(&prefix)?
+                    .strip_suffix(&suffix)
+                    .map(str::to_string)
+            })
+            .filter(|label| !label.is_empty())
+            .collect();
+
+        labels.sort();
+
+        Ok(labels)
+    }
+
+    /// Delete a snapshot of the table.
+    ///
+    /// The `label` of the snapshot to delete.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete_snapshot(&self, label: &str) -> MooResult<()> {
+        match fs::remove_file(self.snapshot_path(label)?) {
+            Ok(_) => {
+                self.debugger.log(
+                    DebugLevel::Info,
+                    format!("Deleted snapshot: {} of table: {}", label, self.name),
+                );
+
+                Ok(())
+            }
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No snapshot found with label: {}", label),
+            }),
+        }
+    }
+
+    /// Move the table behind a read-write lock, so it can be shared between threads.
+    ///
+    /// Returns a `SharedMooTable` that can be cloned into every thread using the table.
+    pub fn into_shared(self) -> SharedMooTable<K, T> {
+        Arc::new(RwLock::new(self))
+    }
+
+    /// Move the table into an `AsyncMooTable`, whose disk writes don't block the async runtime.
+    #[cfg(feature = "tokio")]
+    pub fn into_async(self) -> AsyncMooTable<K, T> {
+        AsyncMooTable::new(self)
+    }
+
+    /// Borrow the table as a handle that can only read it.
+    ///
+    /// Pass the handle to code that should never change the table, so the compiler enforces it.
+    pub fn as_readonly(&self) -> ReadOnlyTable<'_, K, T> {
+        ReadOnlyTable { table: self }
+    }
+
+    /// Start a batch of inserts, updates and deletes that are saved together.
+    ///
+    /// Nothing changes until the batch is committed, which writes the table to disk once for all operations.
+    ///
+    /// Returns a `Batch` to queue the operations on.
+    pub fn batch(&mut self) -> Batch<'_, K, T> {
+        Batch {
+            table: self,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Reserve capacity for at least `additional` more records.
+    ///
+    /// Call this before inserting a large known number of records one by one,
+    /// so the records don't have to be reallocated as the table grows.
+    pub fn reserve(&mut self, additional: usize) {
+        self.records.reserve(additional);
+    }
+
+    /// Insert a new record into the table.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert.
+    pub fn insert(&mut self, key: impl Into<K>, value: T) -> MooResult<()> {
+        self.insert_record(key.into(), value, None)
+    }
+
+    /// Insert a new record into the table, unless a record with the key already exists.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert. An existing record keeps its value.
+    ///
+    /// Unlike `insert` an existing key isn't an error, so callers can branch on the result instead of matching the error.
+    ///
+    /// Returns a `MooResult` with `true` if the record was inserted and `false` if the key already existed.
+    pub fn try_insert(&mut self, key: impl Into<K>, value: T) -> MooResult<bool> {
+        let key = key.into();
+
+        if self.live_value(&key).is_ok() {
+            return Ok(false);
+        }
+
+        self.insert_record(key, value, None)?;
+
+        Ok(true)
+    }
+
+    /// Insert a new record into the table that expires after a while.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert.
+    ///
+    /// The `ttl` after which the record expires. Expired records are treated as missing and
+    /// are removed when they are read or when `purge_expired` is called.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn insert_with_ttl(&mut self, key: impl Into<K>, value: T, ttl: Duration) -> MooResult<()> {
+        let ttl = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+        let expires_at = Utc::now().timestamp_millis().saturating_add(ttl);
+
+        self.insert_record(key.into(), value, Some(expires_at))
+    }
+
+    /// Inserts a new record and saves the table, replacing an expired record with the same key.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn insert_record(&mut self, key: K, value: T, expires_at: Option<i64>) -> MooResult<()> {
+        self.validate(&key, &value)?;
+
+        let mut events = Vec::new();
+        let touched = self.touched.clone();
+        let dirty = self.dirty;
+
+        let expired = match self.key_index.get(&key) {
+            Some(&index) => {
+                if self.records[index].is_live() {
+                    return Err(MooError {
+                        code: MooErrorCodes::Warn,
+                        message: format!("Record with key: {:?} already exists. Use the update method to change its value.", key),
+                    });
+                }
+
+                let expired = self.remove_record(index);
+
+                // A soft deleted record was already reported as deleted when it was marked.
+                if !expired.deleted {
+                    events.push(ChangeEvent::Deleted {
+                        key: expired.key.clone(),
+                    });
+                }
+
+                Some((index, expired))
+            }
+            None => None,
+        };
+
+        let len = self.records.len();
+
+        let record = MooRecord {
+            key,
+            value,
+            expires_at,
+            deleted: false,
+        };
+
+        events.push(ChangeEvent::Inserted {
+            key: record.key.clone(),
+            value: record.value.clone(),
+        });
+
+        self.index_record(&record.key, &record.value);
+        self.mark_changed(&record.key);
+        self.key_index
+            .insert(record.key.clone(), self.records.len());
+        self.records.push(record);
+
+        match self.save() {
+            Ok(_) => {}
+            Err(err) => {
+                self.records.truncate(len);
+
+                if let Some((index, expired)) = expired {
+                    self.records.insert(index, expired);
+                }
+
+                self.rollback(touched, dirty);
+                return Err(err);
+            }
+        }
+
+        if let Some(ChangeEvent::Inserted { key, .. }) = events.last() {
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Insert new record with key: {:?}", key),
+            );
+        }
+
+        self.notify(events)?;
+
+        Ok(())
+    }
+
+    pub fn insert_many(&mut self, data: MooRecords<T, K>) -> MooResult<()> {
+        if data.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: "No records to insert.".to_string(),
+            });
+        }
+
+        let mut keys = HashSet::new();
+
+        for record in &data {
+            if !keys.insert(&record.key) {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!(
+                        "Record with key: {:?} is in the records more than once.",
+                        record.key
+                    ),
+                });
+            }
+
+            self.validate(&record.key, &record.value)?;
+
+            let exist = self.contains_key(&record.key);
+
+            if exist {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!("Record with key: {:?} already exists. Use the update method to change its value.", record.key),
+                });
+            }
+        }
+
+        let touched = self.touched.clone();
+        let dirty = self.dirty;
+        let mut events = Vec::new();
+
+        let mut stale: Vec<usize> = data
+            .iter()
+            .filter_map(|record| self.key_index.get(&record.key).copied())
+            .collect();
+
+        stale.sort_unstable();
+
+        // Expired and soft deleted records with the same keys are replaced, like with `insert`.
+        let removed: Vec<(usize, MooRecord<T, K>)> = stale
+            .into_iter()
+            .rev()
+            .map(|index| (index, self.remove_record(index)))
+            .collect();
+
+        for (_, record) in &removed {
+            // A soft deleted record was already reported as deleted when it was marked.
+            if !record.deleted {
+                events.push(ChangeEvent::Deleted {
+                    key: record.key.clone(),
+                });
+            }
+        }
+
+        let len = self.records.len();
+
+        self.records.reserve(data.len());
+
+        for record in &data {
+            self.index_record(&record.key, &record.value);
+            self.mark_changed(&record.key);
+            self.key_index
+                .insert(record.key.clone(), self.records.len());
+            self.records.push(record.clone());
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Insert new record with key: {:?}", record.key),
+            );
+        }
+
+        match self.save() {
+            Ok(_) => {}
+            Err(err) => {
+                self.records.truncate(len);
+
+                for (index, record) in removed.into_iter().rev() {
+                    self.records.insert(index, record);
+                }
+
+                self.rollback(touched, dirty);
+                return Err(err);
+            }
+        }
+
+        events.extend(data.into_iter().map(|record| ChangeEvent::Inserted {
+            key: record.key,
+            value: record.value,
+        }));
+
+        self.notify(events)?;
+
+        Ok(())
+    }
+
+    /// Sets the value of a record in memory, inserting it if the key doesn't exist yet.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn put_record(&mut self, key: K, value: T) -> ChangeEvent<T, K> {
+        self.mark_changed(&key);
+
+        if let Some(&index) = self.key_index.get(&key) {
+            // Writing over an expired record brings it back without an expiry.
+            if self.records[index].is_expired() {
+                self.records[index].expires_at = None;
+            }
+
+            // A soft deleted record isn't in the numeric indexes, and writing over it brings it back as a new record.
+            let was_deleted = std::mem::take(&mut self.records[index].deleted);
+
+            let old = std::mem::replace(&mut self.records[index].value, value.clone());
+
+            if !was_deleted {
+                self.unindex_record(&key, &old);
+            }
+
+            self.index_record(&key, &value);
+
+            if was_deleted {
+                return ChangeEvent::Inserted { key, value };
+            }
+
+            return ChangeEvent::Updated { key, value };
+        }
+
+        self.index_record(&key, &value);
+        self.key_index.insert(key.clone(), self.records.len());
+        self.records.push(MooRecord {
+            key: key.clone(),
+            value: value.clone(),
+            expires_at: None,
+            deleted: false,
+        });
+
+        ChangeEvent::Inserted { key, value }
+    }
+
+    /// Puts a record read from an export or a snapshot into memory, keeping its expiry and whether it was deleted.
+    ///
+    /// Returns the change event of the record, or `None` for a deleted record that wasn't in the table.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn put_stored_record(&mut self, record: MooRecord<T, K>) -> Option<ChangeEvent<T, K>> {
+        if !record.deleted {
+            let event = self.put_record(record.key.clone(), record.value);
+
+            if let Some(&index) = self.key_index.get(&record.key) {
+                self.records[index].expires_at = record.expires_at;
+            }
+
+            return Some(event);
+        }
+
+        self.mark_changed(&record.key);
+
+        let key = record.key.clone();
+
+        match self.key_index.get(&key).copied() {
+            Some(index) => {
+                let old = std::mem::replace(&mut self.records[index], record);
+
+                if old.deleted {
+                    return None;
+                }
+
+                self.unindex_record(&old.key, &old.value);
+
+                Some(ChangeEvent::Deleted { key })
+            }
+            None => {
+                self.key_index.insert(key, self.records.len());
+                self.records.push(record);
+
+                None
+            }
+        }
+    }
+
+    /// Copies the records with the `keys` and their positions, before they are changed by `put_record`.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn previous_records<'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a K>,
+    ) -> Vec<(usize, MooRecord<T, K>)>
+    where
+        K: 'a,
+    {
+        keys.into_iter()
+            .filter_map(|key| self.key_index.get(key))
+            .map(|&index| (index, self.records[index].clone()))
+            .collect()
+    }
+
+    /// Undoes the records set by `put_record` when they can't be saved.
+    ///
+    /// The records after `len` were inserted and are removed, the `previous` records are put back in place.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn undo_puts(
+        &mut self,
+        len: usize,
+        previous: Vec<(usize, MooRecord<T, K>)>,
+        touched: HashSet<K>,
+        dirty: bool,
+    ) {
+        self.records.truncate(len);
+
+        for (index, record) in previous {
+            self.records[index] = record;
+        }
+
+        self.rollback(touched, dirty);
+    }
+
+    /// Insert a record into the table, or update it if a record with the key already exists.
+    ///
+    /// The `key` of the record to insert or update.
+    ///
+    /// The `value` of the record to insert or update.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn upsert(&mut self, key: impl Into<K>, value: T) -> MooResult<()> {
+        let key = key.into();
+
+        self.validate(&key, &value)?;
+
+        let len = self.records.len();
+        let touched = self.touched.clone();
+        let dirty = self.dirty;
+        let previous = self.previous_records([&key]);
+
+        let event = self.put_record(key, value);
+
+        if let Err(err) = self.save() {
+            self.undo_puts(len, previous, touched, dirty);
+            return Err(err);
+        }
+
+        if let ChangeEvent::Inserted { key, .. } | ChangeEvent::Updated { key, .. } = &event {
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Upserted record with key: {:?}", key),
+            );
+        }
+
+        self.notify(vec![event])
+    }
+
+    /// Insert or update multiple records in the table at once.
+    ///
+    /// The `data` vector containing the records to insert or update. If a key is in the vector more than once
+    /// its last record is kept. The table is saved once for all records.
+    ///
+    /// Returns a `MooResult` with a `BatchReport` of how many records were inserted and how many were updated.
+    pub fn upsert_many(&mut self, data: MooRecords<T, K>) -> MooResult<BatchReport> {
+        if data.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: "No records to upsert.".to_string(),
+            });
+        }
+
+        for record in &data {
+            self.validate(&record.key, &record.value)?;
+        }
+
+        let len = self.records.len();
+        let touched = self.touched.clone();
+        let dirty = self.dirty;
+        let previous = self.previous_records(data.iter().map(|record| &record.key));
+
+        let events: Vec<ChangeEvent<T, K>> = data
+            .into_iter()
+            .map(|record| self.put_record(record.key, record.value))
+            .collect();
+
+        if let Err(err) = self.save() {
+            self.undo_puts(len, previous, touched, dirty);
+            return Err(err);
+        }
+
+        let mut report = BatchReport::default();
+
+        for event in &events {
+            match event {
+                ChangeEvent::Inserted { .. } => report.inserted += 1,
+                _ => report.updated += 1,
+            }
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!(
+                "Upserted {} records, {} inserted and {} updated",
+                events.len(),
+                report.inserted,
+                report.updated
+            ),
+        );
+
+        self.notify(events)?;
+
+        Ok(report)
+    }
+
+    /// Get a record from the table.
+    ///
+    /// The `key` of the record to get. An expired record is removed and returns `NotFound`.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn get<Q>(&mut self, key: &Q) -> MooResult<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        self.record_get();
+
+        if let Some(&index) = self.key_index.get(key) {
+            if self.records[index].is_live() {
+                self.debugger.log(
+                    DebugLevel::Info,
+                    format!("Found record with key: {:?}", key),
+                );
+
+                return Ok(self.records[index].value.clone());
+            }
+
+            if self.records[index].is_expired() {
+                let touched = self.touched.clone();
+                let dirty = self.dirty;
+                let expired = self.remove_record(index);
+
+                if let Err(err) = self.save() {
+                    self.records.insert(index, expired);
+                    self.rollback(touched, dirty);
+                    return Err(err);
+                }
+
+                self.debugger.log(
+                    DebugLevel::Info,
+                    format!("Removed expired record with key: {:?}", key),
+                );
+
+                if !expired.deleted {
+                    self.notify(vec![ChangeEvent::Deleted { key: expired.key }])?;
+                }
+            }
+        }
+
+        self.debugger.log(
+            DebugLevel::Warning,
+            format!("No record found with key: {:?}", key),
+        );
+
+        Err(MooError {
+            code: MooErrorCodes::NotFound,
+            message: format!("No record found with key: {:?}", key),
+        })
+    }
+
+    /// Get a record from the table, or insert a new record if the key doesn't exist yet.
+    ///
+    /// The `key` of the record to get or insert.
+    ///
+    /// The function `f` creating the value to insert. It's only called when the key is missing or its record expired.
+    ///
+    /// Returns a `MooResult` with the existing or inserted value.
+    pub fn get_or_insert_with<F>(&mut self, key: impl Into<K>, f: F) -> MooResult<T>
+    where
+        F: FnOnce() -> T,
+    {
+        self.record_get();
+
+        let key = key.into();
+
+        if let Some(&index) = self.key_index.get(&key) {
+            if self.records[index].is_live() {
+                self.debugger.log(
+                    DebugLevel::Info,
+                    format!("Found record with key: {:?}", key),
+                );
+
+                return Ok(self.records[index].value.clone());
+            }
+        }
+
+        let value = f();
+
+        self.insert_record(key, value.clone(), None)?;
+
+        Ok(value)
+    }
+
+    /// Get a record from the table, or a fallback value if the key doesn't exist.
+    ///
+    /// The `key` of the record to get.
+    ///
+    /// The `default` value returned when there is no record with the key or it expired. It isn't inserted.
+    ///
+    /// Returns the stored value or the `default`.
+    pub fn get_or<Q>(&self, key: &Q, default: T) -> T
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        self.record_get();
+
+        match self.live_value(key) {
+            Ok(value) => value.clone(),
+            Err(_) => default,
+        }
+    }
+
+    /// Get a record from the table, or the default value of `T` if the key doesn't exist.
+    ///
+    /// The `key` of the record to get.
+    ///
+    /// Returns the stored value or `T::default()`. The default isn't inserted.
+    pub fn get_or_default<Q>(&self, key: &Q) -> T
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+        T: Default,
+    {
+        self.record_get();
+
+        match self.live_value(key) {
+            Ok(value) => value.clone(),
+            Err(_) => T::default(),
+        }
+    }
+
+    /// Get multiple records from the table at once.
+    ///
+    /// The `keys` of the records to get. The keys are treated as a set, so a duplicated key only returns its record once.
+    ///
+    /// Returns a `MooResult` with the records in table order.
+    pub fn get_many<Q>(&mut self, keys: Vec<&Q>) -> MooResult<MooRecords<T, K>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        self.record_get();
+
+        let keys = dedupe_keys(&keys);
+        let wanted: HashSet<&Q> = keys.iter().copied().collect();
+        let mut records = Vec::new();
+
+        for record in &self.records {
+            if !record.deleted && wanted.contains(record.key.borrow()) {
+                records.push(record.clone());
+                self.debugger.log(
+                    DebugLevel::Info,
+                    format!("Found record with key: {:?}", record.key),
+                );
+            }
+        }
+
+        if records.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No records found with keys: {:?}", keys),
+            });
+        }
+
+        Ok(records)
+    }
+
+    /// Get multiple records from the table at once and report the keys that weren't found.
+    ///
+    /// The `keys` of the records to get. The keys are treated as a set, so a duplicated key is only looked up once.
+    /// Expired records are reported as missing.
+    ///
+    /// Unlike `get_many` this doesn't fail when none of the keys are found.
+    ///
+    /// Returns a `MooResult` with the found records and the missing keys, both in the order of `keys`.
+    pub fn get_many_checked<Q>(&self, keys: &[&Q]) -> MooResult<(MooRecords<T, K>, Vec<K>)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ToOwned<Owned = K> + ?Sized,
+    {
+        self.record_get();
+
+        let mut records = Vec::new();
+        let mut missing = Vec::new();
+
+        for key in dedupe_keys(keys) {
+            match self.key_index.get(key) {
+                Some(&index) if self.records[index].is_live() => {
+                    records.push(self.records[index].clone())
+                }
+                _ => missing.push(key.to_owned()),
+            }
+        }
+
+        Ok((records, missing))
+    }
+
+    /// Get all the records from the table.
+    ///
+    /// This should be extremely fast as the records are already loaded into memory.
+    /// You can also simply access the `records` field directly, however this method is provided for convenience
+    /// and it supports error handling and logging out of the box. Also keep in mind data should never be modified directly
+    /// using this property as it can cause data corruption with the file persistence.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn get_all(&mut self) -> MooResult<MooRecords<T, K>> {
+        let records: MooRecords<T, K> = self
+            .records
+            .iter()
+            .filter(|record| record.is_live())
+            .cloned()
+            .collect();
+
+        if records.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: "No records found in the table.".to_string(),
+            });
+        }
+
+        self.debugger
+            .log(DebugLevel::Info, format!("Found {} records", records.len()));
+
+        Ok(records)
+    }
+
+    /// Get one page of the records in the table.
+    ///
+    /// The `offset` of the first record on the page, counted in table order. Expired and deleted records are skipped.
+    ///
+    /// The `limit` of records on the page, it must be greater than zero.
+    ///
+    /// Returns a `MooResult` with the records on the page, an empty vector if `offset` is past the last record.
+    pub fn get_page(&self, offset: usize, limit: usize) -> MooResult<MooRecords<T, K>> {
+        if limit == 0 {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: "Page limit must be greater than zero.".to_string(),
+            });
+        }
+
+        Ok(self
+            .records
+            .iter()
+            .filter(|record| record.is_live())
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    /// The number of pages of `limit` records it takes to get every record with `get_page`.
+    ///
+    /// An empty table or a `limit` of zero has no pages.
+    pub fn page_count(&self, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+
+        self.len().div_ceil(limit)
+    }
+
+    /// Borrow all the records in the table without cloning them.
+    ///
+    /// This is the cheapest way to read the whole table, the records can't be changed while the references are held.
+    /// Unlike `get_all` an empty table simply returns an empty vector. Soft deleted records are skipped.
+    pub fn all(&self) -> Vec<&MooRecord<T, K>> {
+        self.iter().collect()
+    }
+
+    /// Iterate over the records in the table in table order without cloning them.
+    ///
+    /// Like `all`, records that expired but haven't been removed yet are included and soft deleted records are skipped.
+    /// `&MooTable` also implements `IntoIterator`, so a table can be looped over directly.
+    pub fn iter(&self) -> RecordIter<'_, K, T> {
+        RecordIter {
+            records: self.records.iter(),
+        }
+    }
+
+    /// Call a closure with every record in the table, in table order, without cloning the records.
+    ///
+    /// The closure `f` called with a reference to each record. Unlike `get_all` no vector of records is built,
+    /// so large tables can be processed without doubling their memory use.
+    ///
+    /// Like `iter`, records that expired but haven't been removed yet are included.
+    pub fn for_each<F>(&self, f: F)
+    where
+        F: FnMut(&MooRecord<T, K>),
+    {
+        self.iter().for_each(f);
+    }
+
+    /// Iterate over the records in the table in key order without cloning them.
+    ///
+    /// Records are kept in insertion order, so every call sorts references to the records,
+    /// which takes `O(n log n)` time and allocates a vector of `n` references.
+    /// Records that expired but haven't been removed yet are skipped.
+    pub fn iter_sorted(&self) -> std::vec::IntoIter<&MooRecord<T, K>>
+    where
+        K: Ord,
+    {
+        let mut records: Vec<&MooRecord<T, K>> = self
+            .records
+            .iter()
+            .filter(|record| record.is_live())
+            .collect();
+
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+
+        records.into_iter()
+    }
+
+    /// Get every record whose key falls within a range, in key order.
+    ///
+    /// The `range` of borrowed keys to get, like `"2024-01-01".."2024-02-01"` for string keys or `&10..=&20` for numeric keys.
+    /// String keys are compared lexicographically, which also orders ISO timestamps by time.
+    ///
+    /// Like `iter_sorted` this sorts the matching records on every call. Records that expired but haven't been removed yet are skipped.
+    ///
+    /// Returns a `MooResult` with the records in the range, an empty vector if no key falls within it.
+    pub fn range<'a, Q, R>(&self, range: R) -> MooResult<MooRecords<T, K>>
+    where
+        K: Borrow<Q> + Ord,
+        Q: Ord + ?Sized + 'a,
+        R: RangeBounds<&'a Q>,
+    {
+        let mut records: MooRecords<T, K> = self
+            .records
+            .iter()
+            .filter(|record| record.is_live() && range.contains(&record.key.borrow()))
+            .cloned()
+            .collect();
+
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(records)
+    }
+
+    /// Get the record with the smallest key.
+    ///
+    /// This scans every record once, without sorting. Records that expired but haven't been removed yet are skipped.
+    ///
+    /// Returns the record, or `None` if the table has no records.
+    pub fn first(&self) -> Option<&MooRecord<T, K>>
+    where
+        K: Ord,
+    {
+        self.records
+            .iter()
+            .filter(|record| record.is_live())
+            .min_by(|a, b| a.key.cmp(&b.key))
+    }
+
+    /// Get the record with the largest key.
+    ///
+    /// This scans every record once, without sorting. Records that expired but haven't been removed yet are skipped.
+    ///
+    /// Returns the record, or `None` if the table has no records.
+    pub fn last(&self) -> Option<&MooRecord<T, K>>
+    where
+        K: Ord,
+    {
+        self.records
+            .iter()
+            .filter(|record| record.is_live())
+            .max_by(|a, b| a.key.cmp(&b.key))
+    }
+
+    /// Iterate over the keys of the records without cloning them.
+    ///
+    /// The keys are in the same order as the records in the table, which is the order they were inserted in.
+    /// Expired and deleted records are skipped.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.records
+            .iter()
+            .filter(|record| record.is_live())
+            .map(|record| &record.key)
+    }
+
+    /// Iterate over the values of the records without cloning them.
+    ///
+    /// The values are in the same order as the records in the table, which is the order they were inserted in.
+    /// Expired and deleted records are skipped.
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.records
+            .iter()
+            .filter(|record| record.is_live())
+            .map(|record| &record.value)
+    }
+
+    /// Whether a record with the `key` is in the table. Expired and deleted records aren't.
+    ///
+    /// Unlike `get` this doesn't clone the value or log anything.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.key_index
+            .get(key)
+            .is_some_and(|&index| self.records[index].is_live())
+    }
+
+    /// Find which of the `keys` have a record in the table, using the key index instead of a lookup per call.
+    ///
+    /// The keys are treated as a set, so duplicated keys are only returned once.
+    ///
+    /// Returns the keys that are in the table, in the order they were given.
+    pub fn which_exist<'a, Q>(&self, keys: &[&'a Q]) -> Vec<&'a Q>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        dedupe_keys(keys)
+            .into_iter()
+            .filter(|key| self.contains_key(*key))
+            .collect()
+    }
+
+    /// Find which of the `keys` don't have a record in the table, the complement of `which_exist`.
+    ///
+    /// Returns the keys that aren't in the table, in the order they were given.
+    pub fn which_missing<'a, Q>(&self, keys: &[&'a Q]) -> Vec<&'a Q>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        dedupe_keys(keys)
+            .into_iter()
+            .filter(|key| !self.contains_key(*key))
+            .collect()
+    }
+
+    /// The number of records in the table. Expired and deleted records aren't counted.
+    pub fn len(&self) -> usize {
+        self.records
+            .iter()
+            .filter(|record| record.is_live())
+            .count()
+    }
+
+    /// The number of records in the table, the same as `len`.
+    ///
+    /// Unlike `get_all` an empty table isn't an error, it simply has a count of zero.
+    pub fn count(&self) -> usize {
+        self.len()
+    }
+
+    /// Whether the table has no records, other than expired and deleted ones.
+    pub fn is_empty(&self) -> bool {
+        !self.records.iter().any(|record| record.is_live())
+    }
+
+    /// Count the records in the table grouped by a value derived from each record.
+    ///
+    /// The `key_fn` returning the group a record value belongs to.
+    ///
+    /// Returns a `HashMap` with the number of records in every group. Expired and deleted records are skipped.
+    pub fn count_by<G, F>(&self, key_fn: F) -> HashMap<G, usize>
+    where
+        G: Eq + Hash,
+        F: Fn(&T) -> G,
+    {
+        let mut counts = HashMap::new();
+
+        for record in self.records.iter().filter(|record| record.is_live()) {
+            *counts.entry(key_fn(&record.value)).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    /// Find all the records whose value matches a predicate.
+    ///
+    /// The `predicate` called with the value of every record. Expired records are skipped.
+    ///
+    /// Returns a `MooResult` with the matching records in table order, an empty vector if nothing matches.
+    pub fn find<F>(&self, predicate: F) -> MooResult<MooRecords<T, K>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        Ok(self
+            .records
+            .iter()
+            .filter(|record| record.is_live() && predicate(&record.value))
+            .cloned()
+            .collect())
+    }
+
+    /// Find all the records whose key starts with a prefix.
+    ///
+    /// The `prefix` the keys must start with, like `user:123:` for keys namespaced as `user:123:profile`.
+    /// Expired records are skipped.
+    ///
+    /// Returns a `MooResult` with the matching records in table order, an empty vector if nothing matches.
+    pub fn scan_prefix(&self, prefix: &str) -> MooResult<MooRecords<T, K>>
+    where
+        K: AsRef<str>,
+    {
+        Ok(self
+            .records
+            .iter()
+            .filter(|record| record.is_live() && record.key.as_ref().starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    /// Find all the records whose key matches a glob pattern.
+    ///
+    /// The `pattern` the keys must match, like `user:*:settings`. A `*` matches any number of characters
+    /// and a `?` matches exactly one, every other character matches itself. Expired records are skipped.
+    ///
+    /// Returns a `MooResult` with the matching records in table order, an empty vector if nothing matches.
+    pub fn scan_pattern(&self, pattern: &str) -> MooResult<MooRecords<T, K>>
+    where
+        K: AsRef<str>,
+    {
+        let pattern: Vec<char> = pattern.chars().collect();
+
+        Ok(self
+            .records
+            .iter()
+            .filter(|record| record.is_live() && glob_match(&pattern, record.key.as_ref()))
+            .cloned()
+            .collect())
+    }
+
+    /// Find the first record whose value matches a predicate.
+    ///
+    /// The `predicate` called with the value of every record until one matches. Expired records are skipped.
+    ///
+    /// Returns the first matching record in table order, or `None` if nothing matches.
+    pub fn find_one<F>(&self, predicate: F) -> Option<MooRecord<T, K>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.records
+            .iter()
+            .find(|record| record.is_live() && predicate(&record.value))
+            .cloned()
+    }
+
+    /// Count the records whose value matches a predicate.
+    ///
+    /// The `predicate` called with the value of every record. Expired records are skipped.
+    pub fn count_where<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.records
+            .iter()
+            .filter(|record| record.is_live() && predicate(&record.value))
+            .count()
+    }
+
+    /// Count the records matching a predicate on the whole record, to see what a bulk delete would affect.
+    ///
+    /// The `predicate` called with every record. Expired and deleted records are skipped, like bulk deletes skip them.
+    pub fn count_matching<F>(&self, mut predicate: F) -> usize
+    where
+        F: FnMut(&MooRecord<T, K>) -> bool,
+    {
+        self.records
+            .iter()
+            .filter(|record| record.is_live() && predicate(record))
+            .count()
+    }
+
+    /// Update a record in the table.
+    ///
+    /// The `key` of the record to update.
+    ///
+    /// The `value` of the record to update.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn update<Q>(&mut self, key: &Q, value: T) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let found = self.key_index.get(key).copied();
+
+        if let Some(index) = found.filter(|&index| self.records[index].is_live()) {
+            let key = self.records[index].key.clone();
+            self.validate(&key, &value)?;
+
+            let touched = self.touched.clone();
+            let dirty = self.dirty;
+            let old = std::mem::replace(&mut self.records[index].value, value.clone());
+            self.unindex_record(&key, &old);
+            self.index_record(&key, &value);
+            self.mark_changed(&key);
+
+            if let Err(err) = self.save() {
+                self.records[index].value = old;
+                self.rollback(touched, dirty);
+                return Err(err);
+            }
+
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Updated record with key: {:?}", key),
+            );
+
+            self.notify(vec![ChangeEvent::Updated { key, value }])?;
+
+            return Ok(());
+        }
+
+        self.debugger.log(
+            DebugLevel::Warning,
+            format!("No record found with key: {:?}", key),
+        );
+
+        Err(MooError {
+            code: MooErrorCodes::NotFound,
+            message: format!("No record found with key: {:?}", key),
+        })
+    }
+
+    /// Update a record in the table only if its current value matches a predicate.
+    ///
+    /// The `key` of the record to update.
+    ///
+    /// The `predicate` called with the current value, the record is only updated and saved when it returns `true`.
+    /// This makes checks like "only debit if the balance is high enough" a single step on the table.
+    ///
+    /// The `value` to update the record with.
+    ///
+    /// Returns a `MooResult` with whether the record was updated, or `NotFound` if there is no record with the key.
+    pub fn update_if<Q, F>(&mut self, key: &Q, predicate: F, value: T) -> MooResult<bool>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+        F: Fn(&T) -> bool,
+    {
+        let current = match self.key_index.get(key) {
+            Some(&index) if self.records[index].is_live() => &self.records[index].value,
+            _ => {
+                return Err(MooError {
+                    code: MooErrorCodes::NotFound,
+                    message: format!("No record found with key: {:?}", key),
+                })
+            }
+        };
+
+        if !predicate(current) {
+            return Ok(false);
+        }
+
+        self.update(key, value)?;
+
+        Ok(true)
+    }
+
+    /// Modify the value of a record in place with a closure.
+    ///
+    /// The `key` of the record to modify.
+    ///
+    /// The closure `f` called with a mutable reference to the stored value, so the value isn't cloned out and written back.
+    /// This makes changes like "increment this nested counter" a single call.
+    ///
+    /// Returns a `MooResult` with the result of the action, or `NotFound` if there is no record with the key.
+    pub fn modify<Q, F>(&mut self, key: &Q, f: F) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+        F: FnOnce(&mut T),
+    {
+        let index = match self.key_index.get(key) {
+            Some(&index) if self.records[index].is_live() => index,
+            _ => {
+                self.debugger.log(
+                    DebugLevel::Warning,
+                    format!("No record found with key: {:?}", key),
+                );
+
+                return Err(MooError {
+                    code: MooErrorCodes::NotFound,
+                    message: format!("No record found with key: {:?}", key),
+                });
+            }
+        };
+
+        let touched = self.touched.clone();
+        let dirty = self.dirty;
+        let previous = self.records[index].value.clone();
+        let record = &mut self.records[index];
+
+        for index in self.numeric_indexes.values_mut() {
+            index.remove(&record.key, &record.value);
+        }
+
+        f(&mut record.value);
+
+        for index in self.numeric_indexes.values_mut() {
+            index.add(&record.key, &record.value);
+        }
+
+        let key = record.key.clone();
+        let value = record.value.clone();
+
+        self.mark_changed(&key);
+
+        if let Err(err) = self.save() {
+            self.records[index].value = previous;
+            self.rollback(touched, dirty);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Modified record with key: {:?}", key),
+        );
+
+        self.notify(vec![ChangeEvent::Updated { key, value }])
+    }
+
+    /// Combine an operand with the value of a record, creating the record if it doesn't exist.
+    ///
+    /// The `key` of the record to merge into.
+    ///
+    /// The `operand` to combine with the stored value, like the amount to add to a counter.
+    ///
+    /// The function `f` gets the stored value, or `None` if the key is missing or expired, and the operand.
+    /// It returns the new value, which is stored and saved.
+    ///
+    /// Returns a `MooResult` with the new value.
+    pub fn merge<F>(&mut self, key: impl Into<K>, operand: T, f: F) -> MooResult<T>
+    where
+        F: FnOnce(Option<&T>, T) -> T,
+    {
+        let key = key.into();
+        let merged = f(self.live_value(&key).ok(), operand);
+
+        self.upsert(key, merged.clone())?;
+
+        Ok(merged)
+    }
+
+    /// Borrow the value of a record mutably, and save it once the returned guard goes out of scope.
+    ///
+    /// The `key` of the record to borrow.
+    ///
+    /// The guard derefs to the stored value, so several fields can be changed without cloning the value out.
+    /// The table is only saved if the value was borrowed mutably. Use `EntryGuard::commit` to get the result of the save,
+    /// a failed save when the guard is dropped can only be logged.
+    ///
+    /// Returns a `MooResult` with the guard, or `NotFound` if there is no record with the key.
+    pub fn entry<Q>(&mut self, key: &Q) -> MooResult<EntryGuard<'_, K, T>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let index = match self.key_index.get(key) {
+            Some(&index) if self.records[index].is_live() => index,
+            _ => {
+                self.debugger.log(
+                    DebugLevel::Warning,
+                    format!("No record found with key: {:?}", key),
+                );
+
+                return Err(MooError {
+                    code: MooErrorCodes::NotFound,
+                    message: format!("No record found with key: {:?}", key),
+                });
+            }
+        };
+
+        Ok(EntryGuard {
+            table: self,
+            index,
+            changed: false,
+            committed: false,
+        })
+    }
+
+    /// Replace every record in the table with a new set of records.
+    ///
+    /// The `records` to store in the table. Their keys must be unique, and every record is checked by the validator.
+    ///
+    /// The table file is rewritten once with the new records through a temporary file, so it never holds a mix of
+    /// the old and new records or is empty in between. Records whose key is in both sets are reported as updated.
+    ///
+    /// Returns a `MooResult` with the result of the action. The table is left unchanged if it fails.
+    pub fn replace_all(&mut self, records: MooRecords<T, K>) -> MooResult<()> {
+        let mut keys = HashSet::new();
+
+        for record in &records {
+            if !keys.insert(&record.key) {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!(
+                        "Record with key: {:?} is in the records more than once.",
+                        record.key
+                    ),
+                });
+            }
+
+            self.validate(&record.key, &record.value)?;
+        }
+
+        let state = self.save_state();
+        let old_keys: HashSet<K> = self
+            .records
+            .iter()
+            .map(|record| record.key.clone())
+            .collect();
+
+        let mut events: Vec<ChangeEvent<T, K>> = Vec::new();
+
+        for record in self.records.drain(..) {
+            if !keys.contains(&record.key) {
+                events.push(ChangeEvent::Deleted { key: record.key });
+            }
+        }
+
+        for key in &old_keys {
+            self.mark_changed(key);
+        }
+
+        self.clear_indexes();
+
+        for record in records {
+            let expires_at = record.expires_at;
+            let event = match self.put_record(record.key, record.value) {
+                ChangeEvent::Inserted { key, value } if old_keys.contains(&key) => {
+                    ChangeEvent::Updated { key, value }
+                }
+                event => event,
+            };
+
+            if let ChangeEvent::Inserted { key, .. } | ChangeEvent::Updated { key, .. } = &event {
+                if let Some(&index) = self.key_index.get(key) {
+                    self.records[index].expires_at = expires_at;
+                }
+            }
+
+            events.push(event);
+        }
+
+        // A whole new data set is written as a new table file instead of being appended to the write-ahead log.
+        let written = if self.config.autosave && !self.is_memory() {
+            self.serialize_records()
+                .and_then(|serialized_records| self.write_file(&serialized_records))
+        } else {
+            self.save()
+        };
+
+        if let Err(err) = written {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Replaced all records with {} records", self.records.len()),
+        );
+
+        self.notify(events)
+    }
+
+    /// Update multiple records in the table at once.
+    ///
+    /// The `update` vector containing the records to update. The keys are treated as a set,
+    /// if a key is in the vector more than once only its last record is used.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn update_many(&mut self, update: MooRecords<T, K>) -> MooResult<()> {
+        if update.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: "No records to update.".to_string(),
+            });
+        }
+
+        for record in &update {
+            self.validate(&record.key, &record.value)?;
+        }
+
+        let updates: HashMap<&K, &T> = update
+            .iter()
+            .map(|record| (&record.key, &record.value))
+            .collect();
+
+        let state = self.save_state();
+        let mut events = Vec::new();
+
+        for record in self.records.iter_mut().filter(|record| record.is_live()) {
+            if let Some(value) = updates.get(&record.key) {
+                for index in self.numeric_indexes.values_mut() {
+                    index.remove(&record.key, &record.value);
+                    index.add(&record.key, value);
+                }
+
+                record.value = (*value).clone();
+                self.dirty = true;
+                self.touched.insert(record.key.clone());
+                self.debugger.log(
+                    DebugLevel::Info,
+                    format!("Updated record with key: {:?}", record.key),
+                );
+
+                events.push(ChangeEvent::Updated {
+                    key: record.key.clone(),
+                    value: record.value.clone(),
+                });
+            }
+        }
+
+        match self.save() {
+            Ok(_) => self.notify(events),
+            Err(err) => {
+                self.restore_state(state);
+                Err(err)
+            }
+        }
+    }
+
+    /// Rename the key of a record, keeping its value and its position in the table.
+    ///
+    /// The `old` key of the record to rename, it must exist.
+    ///
+    /// The `new` key of the record, it must not be taken by another record.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn rename_key<Q>(&mut self, old: &Q, new: &Q) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ToOwned<Owned = K> + ?Sized,
+    {
+        self.rename_many(&[(old, new)])?;
+
+        Ok(())
+    }
+
+    /// Rename the keys of multiple records at once.
+    ///
+    /// The `mapping` of current keys to their new keys. Every current key must exist and every new key must be free,
+    /// a new key may only be taken by a record that is renamed away in the same mapping.
+    ///
+    /// Nothing is renamed if any part of the mapping is invalid, otherwise all renames are saved at once.
+    ///
+    /// Returns a `MooResult` with the number of renamed records.
+    pub fn rename_many<Q>(&mut self, mapping: &[(&Q, &Q)]) -> MooResult<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ToOwned<Owned = K> + ?Sized,
+    {
+        let mut sources = HashSet::new();
+
+        for (from, _) in mapping {
+            if !sources.insert(*from) {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!("Record with key: {:?} is renamed more than once.", from),
+                });
+            }
+
+            match self.key_index.get(*from) {
+                Some(&index) if self.records[index].is_live() => {}
+                _ => {
+                    return Err(MooError {
+                        code: MooErrorCodes::NotFound,
+                        message: format!("No record found with key: {:?}", from),
+                    })
+                }
+            }
+        }
+
+        let mut destinations = HashSet::new();
+        let mut stale = Vec::new();
+
+        for (_, to) in mapping {
+            if !destinations.insert(*to) {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!("More than one record is renamed to key: {:?}", to),
+                });
+            }
+
+            match self.key_index.get(*to) {
+                Some(&index) if !sources.contains(to) => {
+                    if self.records[index].is_live() {
+                        return Err(MooError {
+                            code: MooErrorCodes::Warn,
+                            message: format!("Record with key: {:?} already exists.", to),
+                        });
+                    }
+
+                    stale.push(index);
+                }
+                _ => {}
+            }
+        }
+
+        let state = self.save_state();
+
+        // All deletes are sent before the inserts, so replaying the events works for swapped keys.
+        let mut events = Vec::new();
+        let mut inserted = Vec::new();
+
+        stale.sort_unstable();
+
+        // Expired and soft deleted records are replaced by the records renamed to their keys.
+        for index in stale.into_iter().rev() {
+            let record = self.remove_record(index);
+
+            if !record.deleted {
+                events.push(ChangeEvent::Deleted { key: record.key });
+            }
+        }
+
+        let positions: Vec<usize> = mapping
+            .iter()
+            .map(|(from, _)| self.key_index[*from])
+            .collect();
+
+        for ((_, to), index) in mapping.iter().zip(positions) {
+            let value = self.records[index].value.clone();
+            let to = (*to).to_owned();
+            let from = std::mem::replace(&mut self.records[index].key, to.clone());
+
+            self.unindex_record(&from, &value);
+            self.index_record(&to, &value);
+            self.mark_changed(&from);
+            self.mark_changed(&to);
+
+            events.push(ChangeEvent::Deleted { key: from });
+            inserted.push(ChangeEvent::Inserted { key: to, value });
+        }
+
+        events.extend(inserted);
+
+        // Swapped keys would overwrite each other one by one, so the key index is rebuilt at once.
+        self.key_index.clear();
+        self.reindex_keys_from(0);
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Renamed {} records", mapping.len()),
+        );
+
+        self.notify(events)?;
+
+        Ok(mapping.len())
+    }
+
+    /// Modify the records in the table in place with a closure.
+    ///
+    /// The closure `f` is called with the key and a mutable reference to the value of every record,
+    /// and returns `true` when it changed the value.
+    ///
+    /// The table is saved once if any record was changed.
+    ///
+    /// Returns a `MooResult` with the number of changed records.
+    pub fn modify_each<F>(&mut self, mut f: F) -> MooResult<usize>
+    where
+        F: FnMut(&K, &mut T) -> bool,
+    {
+        let state = self.save_state();
+        let mut events = Vec::new();
+
+        for record in self.records.iter_mut().filter(|record| !record.deleted) {
+            if f(&record.key, &mut record.value) {
+                events.push(ChangeEvent::Updated {
+                    key: record.key.clone(),
+                    value: record.value.clone(),
+                });
+            }
+        }
+
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        for event in &events {
+            if let ChangeEvent::Updated { key, .. } = event {
+                self.mark_changed(key);
+            }
+        }
+
+        self.rebuild_indexes();
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Modified {} records", events.len()),
+        );
+
+        let count = events.len();
+
+        self.notify(events)?;
+
+        Ok(count)
+    }
+
+    /// Delete a record from the table.
+    ///
+    /// The `key` of the record to delete.
+    ///
+    /// With `soft_delete` enabled in the config the record is only marked as deleted, see `restore`.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete<Q>(&mut self, key: &Q) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let found = self.key_index.get(key).copied();
+
+        if let Some(index) = found.filter(|&index| self.records[index].is_live()) {
+            let touched = self.touched.clone();
+            let dirty = self.dirty;
+            let record = self.delete_record(index);
+
+            if let Err(err) = self.save() {
+                match self.config.soft_delete {
+                    true => self.records[index].deleted = false,
+                    false => self.records.insert(index, record),
+                }
+
+                self.rollback(touched, dirty);
+                return Err(err);
+            }
+
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Deleted record with key: {:?}", key),
+            );
+
+            self.notify(vec![ChangeEvent::Deleted { key: record.key }])?;
+
+            return Ok(());
+        }
+
+        self.debugger.log(
+            DebugLevel::Warning,
+            format!("No record found with key: {:?}", key),
+        );
+
+        Err(MooError {
+            code: MooErrorCodes::NotFound,
+            message: format!("No record found with key: {:?}", key),
+        })
+    }
+
+    /// Deletes multiple records from the table at once.
+    ///
+    /// The `keys` of the records to delete. The keys are treated as a set, so duplicated keys are ignored.
+    ///
+    /// With `soft_delete` enabled in the config the records are only marked as deleted, see `restore`.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete_many<Q>(&mut self, keys: Vec<&Q>) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        self.delete_many_with(keys, RunMode::Apply)?;
+
+        Ok(())
+    }
+
+    /// Deletes multiple records from the table at once, or only counts them.
+    ///
+    /// The `keys` of the records to delete, like with `delete_many`.
+    ///
+    /// The `mode` deciding whether the records are deleted, or only counted with `RunMode::DryRun`.
+    ///
+    /// Returns a `MooResult` with the number of records that were deleted, or would be.
+    pub fn delete_many_with<Q>(&mut self, keys: Vec<&Q>, mode: RunMode) -> MooResult<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let keys = dedupe_keys(&keys);
+        let wanted: HashSet<&Q> = keys.iter().copied().collect();
+
+        if mode == RunMode::DryRun {
+            let count = self.count_matching(|record| wanted.contains(record.key.borrow()));
+
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Dry run, would delete {} records", count),
+            );
+
+            return Ok(count);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Deleting records with keys: {:?}", keys),
+        );
+
+        let state = self.save_state();
+        let mut events = Vec::new();
+        let numeric_indexes = &mut self.numeric_indexes;
+        let soft_delete = self.config.soft_delete;
+
+        self.records.retain_mut(|record| {
+            if !record.is_live() || !wanted.contains(record.key.borrow()) {
+                return true;
+            }
+
+            for index in numeric_indexes.values_mut() {
+                index.remove(&record.key, &record.value);
+            }
+
+            events.push(ChangeEvent::Deleted {
+                key: record.key.clone(),
+            });
+
+            // Soft deleted records stay in the table, only marked as deleted.
+            record.deleted = true;
+            soft_delete
+        });
+
+        for event in &events {
+            if let ChangeEvent::Deleted { key } = event {
+                self.mark_changed(key);
+            }
+        }
+
+        self.key_index.clear();
+        self.reindex_keys_from(0);
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        let count = events.len();
+
+        self.notify(events)?;
+
+        Ok(count)
+    }
+
+    /// Keep only the records matching a predicate, like `Vec::retain`.
+    ///
+    /// The closure `keep` is called with every record and returns `false` for the records to delete.
+    /// Expired and deleted records are skipped. With `soft_delete` enabled in the config the records are only marked as deleted.
+    ///
+    /// The table is saved once if any record was deleted, and left unchanged if it can't be saved.
+    ///
+    /// Returns a `MooResult` with the number of deleted records.
+    pub fn retain<F>(&mut self, keep: F) -> MooResult<usize>
+    where
+        F: FnMut(&MooRecord<T, K>) -> bool,
+    {
+        self.retain_with(keep, RunMode::Apply)
+    }
+
+    /// Keep only the records matching a predicate, or only count the records it would delete.
+    ///
+    /// The closure `keep` returns `false` for the records to delete, like with `retain`.
+    ///
+    /// The `mode` deciding whether the records are deleted, or only counted with `RunMode::DryRun`.
+    ///
+    /// Returns a `MooResult` with the number of records that were deleted, or would be.
+    pub fn retain_with<F>(&mut self, mut keep: F, mode: RunMode) -> MooResult<usize>
+    where
+        F: FnMut(&MooRecord<T, K>) -> bool,
+    {
+        if mode == RunMode::DryRun {
+            let count = self.count_matching(|record| !keep(record));
+
+            self.debugger.log(
+                DebugLevel::Info,
+                format!("Dry run, would delete {} records", count),
+            );
+
+            return Ok(count);
+        }
+
+        let state = self.save_state();
+        let mut events = Vec::new();
+        let numeric_indexes = &mut self.numeric_indexes;
+        let soft_delete = self.config.soft_delete;
+
+        self.records.retain_mut(|record| {
+            if !record.is_live() || keep(record) {
+                return true;
+            }
+
+            for index in numeric_indexes.values_mut() {
+                index.remove(&record.key, &record.value);
+            }
+
+            events.push(ChangeEvent::Deleted {
+                key: record.key.clone(),
+            });
+
+            // Soft deleted records stay in the table, only marked as deleted.
+            record.deleted = true;
+            soft_delete
+        });
+
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        for event in &events {
+            if let ChangeEvent::Deleted { key } = event {
+                self.mark_changed(key);
+            }
+        }
+
+        self.key_index.clear();
+        self.reindex_keys_from(0);
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!(
+                "Deleted {} records not matching the predicate",
+                events.len()
+            ),
+        );
+
+        let count = events.len();
+
+        self.notify(events)?;
+
+        Ok(count)
+    }
+
+    /// Remove every expired record from the table.
+    ///
+    /// The table is saved once if any record expired.
+    ///
+    /// Returns a `MooResult` with the number of removed records.
+    pub fn purge_expired(&mut self) -> MooResult<usize> {
+        let state = self.save_state();
+        let mut events = Vec::new();
+        let numeric_indexes = &mut self.numeric_indexes;
+
+        self.records.retain(|record| {
+            if record.is_expired() {
+                for index in numeric_indexes.values_mut() {
+                    index.remove(&record.key, &record.value);
+                }
+
+                events.push(ChangeEvent::Deleted {
+                    key: record.key.clone(),
+                });
+                return false;
+            }
+
+            true
+        });
+
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        for event in &events {
+            if let ChangeEvent::Deleted { key } = event {
+                self.mark_changed(key);
+            }
+        }
+
+        self.key_index.clear();
+        self.reindex_keys_from(0);
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Purged {} expired records", events.len()),
+        );
+
+        let count = events.len();
+
+        self.notify(events)?;
+
+        Ok(count)
+    }
+
+    /// Bring back a record that was soft deleted.
+    ///
+    /// The `key` of the deleted record to restore. Records are only kept after a delete when `soft_delete`
+    /// is enabled in the config.
+    ///
+    /// Returns a `MooResult` with the result of the action, or `NotFound` if there is no deleted record with the key.
+    pub fn restore<Q>(&mut self, key: &Q) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let index = match self.key_index.get(key) {
+            Some(&index) if self.records[index].deleted => index,
+            _ => {
+                self.debugger.log(
+                    DebugLevel::Warning,
+                    format!("No deleted record found with key: {:?}", key),
+                );
+
+                return Err(MooError {
+                    code: MooErrorCodes::NotFound,
+                    message: format!("No deleted record found with key: {:?}", key),
+                });
+            }
+        };
+
+        self.records[index].deleted = false;
+
+        let key = self.records[index].key.clone();
+        let value = self.records[index].value.clone();
+
+        self.index_record(&key, &value);
+        self.mark_changed(&key);
+
+        if let Err(err) = self.save() {
+            self.records[index].deleted = true;
+            self.unindex_record(&key, &value);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Restored record with key: {:?}", key),
+        );
+
+        self.notify(vec![ChangeEvent::Inserted { key, value }])
+    }
+
+    /// Permanently remove every soft deleted record from the table.
+    ///
+    /// Subscribers were already told about the deletes when the records were marked, so no events are sent.
+    ///
+    /// Returns a `MooResult` with the number of records that were removed.
+    pub fn purge_deleted(&mut self) -> MooResult<usize> {
+        let state = self.save_state();
+
+        let purged: Vec<K> = self
+            .records
+            .iter()
+            .filter(|record| record.deleted)
+            .map(|record| record.key.clone())
+            .collect();
+
+        if purged.is_empty() {
+            return Ok(0);
+        }
+
+        self.records.retain(|record| !record.deleted);
+
+        for key in &purged {
+            self.mark_changed(key);
+        }
+
+        self.key_index.clear();
+        self.reindex_keys_from(0);
+
+        if let Err(err) = self.save() {
+            self.restore_state(state);
+            return Err(err);
+        }
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Purged {} deleted records", purged.len()),
+        );
+
+        Ok(purged.len())
+    }
+
+    /// Deletes all the records from the table.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete_all(&mut self) -> MooResult<()> {
+        self.debugger.log(DebugLevel::Info, "Deleting all records");
+
+        let state = self.save_state();
+        let events: Vec<ChangeEvent<T, K>> = self
+            .records
+            .drain(..)
+            .map(|record| ChangeEvent::Deleted { key: record.key })
+            .collect();
+
+        for event in &events {
+            if let ChangeEvent::Deleted { key } = event {
+                self.mark_changed(key);
+            }
+        }
+
+        self.clear_indexes();
+
+        match self.save() {
+            Ok(_) => self.notify(events),
+            Err(err) => {
+                self.restore_state(state);
+                Err(err)
+            }
+        }
+    }
+
+    /// Create a numeric range index over a field of the record values.
+    ///
+    /// The `name` of the index, used to query it with `range_by_index`.
+    ///
+    /// The `extractor` returning the number to index from a value. Integer fields can simply be cast to `f64`.
+    ///
+    /// The index is built from the current records and kept up to date on every write.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn create_numeric_index<F>(&mut self, name: &str, extractor: F) -> MooResult<()>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        if self.numeric_indexes.contains_key(name) {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: format!("Index with name: {} already exists.", name),
+            });
+        }
+
+        let mut index = NumericIndex::new(extractor);
+
+        for record in self.records.iter().filter(|record| !record.deleted) {
+            index.add(&record.key, &record.value);
+        }
+
+        self.numeric_indexes.insert(name.to_string(), index);
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!("Created numeric index with name: {}", name),
+        );
+
+        Ok(())
+    }
+
+    /// Remove a numeric range index from the table.
+    ///
+    /// The `name` of the index to remove.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn drop_numeric_index(&mut self, name: &str) -> MooResult<()> {
+        match self.numeric_indexes.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No index found with name: {}", name),
+            }),
+        }
+    }
+
+    /// Get all the records whose indexed number falls within a range.
+    ///
+    /// The `name` of the numeric index to query.
+    ///
+    /// The `range` of numbers to match, for example `10.0..=100.0`.
+    ///
+    /// Returns a `MooResult` with the matching records ordered by the indexed number.
+    pub fn range_by_index<R>(&self, name: &str, range: R) -> MooResult<MooRecords<T, K>>
+    where
+        R: RangeBounds<f64>,
+    {
+        let index = match self.numeric_indexes.get(name) {
+            Some(index) => index,
+            None => {
+                return Err(MooError {
+                    code: MooErrorCodes::NotFound,
+                    message: format!("No index found with name: {}", name),
+                })
+            }
+        };
+
+        let records: MooRecords<T, K> = index
+            .range(range)
+            .into_iter()
+            .filter_map(|key| self.key_index.get(key))
+            .map(|&position| self.records[position].clone())
+            .collect();
+
+        self.debugger.log(
+            DebugLevel::Info,
+            format!(
+                "Found {} records in index with name: {}",
+                records.len(),
+                name
+            ),
+        );
+
+        Ok(records)
+    }
+}
+
+impl<K, T> MooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned + Counter,
+{
+    /// Add to the numeric value of a record and save it.
+    ///
+    /// The `key` of the record to increment.
+    ///
+    /// The amount to increment the value `by`.
+    ///
+    /// Returns a `MooResult` with the new value, or a `Warn` error if it would overflow the type of the value.
+    pub fn increment<Q>(&mut self, key: &Q, by: T) -> MooResult<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let value = match self.live_value(key)?.checked_add(by) {
+            Some(value) => value,
+            None => {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!(
+                        "Incrementing record with key: {:?} overflows its value.",
+                        key
+                    ),
+                })
+            }
+        };
+
+        self.update(key, value)?;
+
+        Ok(value)
+    }
+
+    /// Subtract from the numeric value of a record and save it.
+    ///
+    /// The `key` of the record to decrement.
+    ///
+    /// The amount to decrement the value `by`.
+    ///
+    /// Returns a `MooResult` with the new value, or a `Warn` error if it would overflow the type of the value.
+    pub fn decrement<Q>(&mut self, key: &Q, by: T) -> MooResult<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        let value = match self.live_value(key)?.checked_sub(by) {
+            Some(value) => value,
+            None => {
+                return Err(MooError {
+                    code: MooErrorCodes::Warn,
+                    message: format!(
+                        "Decrementing record with key: {:?} overflows its value.",
+                        key
+                    ),
+                })
+            }
+        };
+
+        self.update(key, value)?;
+
+        Ok(value)
+    }
+}
+
+impl<T> MooTable<String, T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Get a record whose key is a number stored as a string.
+    ///
+    /// The `key` of the record to get. It's formatted without leading zeros, like `insert_u64` stores it.
+    /// Tables keyed by `u64` instead of `String` store numeric keys as numbers and don't need this.
+    ///
+    /// Returns a `MooResult` with the value, or `NotFound` if there is no record with the key.
+    pub fn get_by_u64(&self, key: u64) -> MooResult<T> {
+        self.record_get();
+
+        self.live_value(key.to_string().as_str()).cloned()
+    }
+
+    /// Insert a new record under a number stored as a string.
+    ///
+    /// The `key` of the record to insert. It's formatted without leading zeros.
+    ///
+    /// The `value` of the record to insert.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn insert_u64(&mut self, key: u64, value: T) -> MooResult<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    /// Get the keys of the records that are numbers, in table order.
+    ///
+    /// Only keys formatted like `insert_u64` formats them are returned, so keys like `007` or `-1` are skipped.
+    pub fn keys_as_u64(&self) -> Vec<u64> {
+        self.records
+            .iter()
+            .filter(|record| record.is_live())
+            .filter_map(|record| {
+                let key = record.key.parse::<u64>().ok()?;
+
+                (key.to_string() == record.key).then_some(key)
+            })
+            .collect()
+    }
+}
+
+/// The in-memory state of a table before a change.
+struct TableState<T, K>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    records: MooRecords<T, K>,
+    touched: HashSet<K>,
+    dirty: bool,
+}
+
+/// An operation queued in a `Batch`.
+#[derive(Debug)]
+enum BatchOp<K, T> {
+    Insert(K, T),
+    Update(K, T),
+    Delete(K),
+}
+
+/// A batch of inserts, updates and deletes on a table that are saved together.
+///
+/// Create one with `MooTable::batch`, queue the operations and `commit` it.
+/// Dropping a batch without committing it discards the queued operations.
+#[derive(Debug)]
+pub struct Batch<'a, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// The table the batch is applied to.
+    table: &'a mut MooTable<K, T>,
+    /// The queued operations, in the order they are applied.
+    ops: Vec<BatchOp<K, T>>,
+}
+
+impl<K, T> Batch<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Queue the insert of a new record.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert.
+    pub fn insert(mut self, key: impl Into<K>, value: T) -> Self {
+        self.ops.push(BatchOp::Insert(key.into(), value));
+        self
+    }
+
+    /// Queue the update of an existing record.
+    ///
+    /// The `key` of the record to update.
+    ///
+    /// The `value` of the record to update.
+    pub fn update(mut self, key: impl Into<K>, value: T) -> Self {
+        self.ops.push(BatchOp::Update(key.into(), value));
+        self
+    }
+
+    /// Queue the delete of an existing record.
+    ///
+    /// The `key` of the record to delete.
+    pub fn delete(mut self, key: impl Into<K>) -> Self {
+        self.ops.push(BatchOp::Delete(key.into()));
+        self
+    }
+
+    /// Apply every queued operation and save the table once.
+    ///
+    /// If any operation is invalid, like inserting a key that already exists, or the table can't be saved,
+    /// none of the operations are applied and the table is left as it was.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn commit(self) -> MooResult<()> {
+        let table = self.table;
+        let state = table.save_state();
+        let count = self.ops.len();
+        let mut events = Vec::new();
+
+        for op in self.ops {
+            if let Err(err) = Self::apply(table, op, &mut events) {
+                table.restore_state(state);
+                return Err(err);
+            }
+        }
+
+        if let Err(err) = table.save() {
+            table.restore_state(state);
+            return Err(err);
+        }
+
+        table.debugger.log(
+            DebugLevel::Info,
+            format!("Committed batch of {} operations", count),
+        );
+
+        table.notify(events)
+    }
+
+    /// Applies a single queued operation to the table in memory.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn apply(
+        table: &mut MooTable<K, T>,
+        op: BatchOp<K, T>,
+        events: &mut Vec<ChangeEvent<T, K>>,
+    ) -> MooResult<()> {
+        match op {
+            BatchOp::Insert(key, value) => {
+                table.validate(&key, &value)?;
+
+                if let Some(&index) = table.key_index.get(&key) {
+                    if table.records[index].is_live() {
+                        return Err(MooError {
+                            code: MooErrorCodes::Warn,
+                            message: format!("Record with key: {:?} already exists. Use the update method to change its value.", key),
+                        });
+                    }
+
+                    let expired = table.remove_record(index);
+
+                    if !expired.deleted {
+                        events.push(ChangeEvent::Deleted { key: expired.key });
+                    }
+                }
+
+                events.push(table.put_record(key, value));
+            }
+            BatchOp::Update(key, value) => {
+                if !table.contains_key(&key) {
+                    return Err(MooError {
+                        code: MooErrorCodes::NotFound,
+                        message: format!("No record found with key: {:?}", key),
+                    });
+                }
+
+                table.validate(&key, &value)?;
+                events.push(table.put_record(key, value));
+            }
+            BatchOp::Delete(key) => match table.key_index.get(&key) {
+                Some(&index) if table.records[index].is_live() => {
+                    let record = table.delete_record(index);
+                    events.push(ChangeEvent::Deleted { key: record.key });
+                }
+                _ => {
+                    return Err(MooError {
+                        code: MooErrorCodes::NotFound,
+                        message: format!("No record found with key: {:?}", key),
+                    })
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+impl<K, T> Drop for MooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Flushes the changes that weren't saved yet, like when autosave is disabled, before the table is dropped.
+    ///
+    /// Nothing is written when the table file was deleted or replaced since this table last read or wrote it,
+    /// so a stale handle can't bring a deleted table back or overwrite newer changes.
+    ///
+    /// Drop can't return an error, so a failed flush is logged instead.
+    fn drop(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        if !self.is_memory() && file_stamp(&self.file_path) != self.stamp {
+            self.debugger.log(
+                DebugLevel::Warning,
+                format!(
+                    "Discarding unsaved changes to table: {}, its file was deleted or changed since it was loaded.",
+                    self.name
+                ),
+            );
+            return;
+        }
+
+        if let Err(err) = self.flush() {
+            self.debugger.log(
+                DebugLevel::Error,
+                format!(
+                    "Failed to flush table: {} before dropping it: {}",
+                    self.name, err.message
+                ),
+            );
+        }
+    }
+}
+
+/// A handle to a table that can only read its records. Create one with `MooTable::as_readonly`.
+///
+/// Expired records are skipped like in the table, but unlike `MooTable::get` they aren't removed from it.
+#[derive(Debug)]
+pub struct ReadOnlyTable<'a, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// The table the handle reads.
+    table: &'a MooTable<K, T>,
+}
+
+impl<K, T> Clone for ReadOnlyTable<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, T> Copy for ReadOnlyTable<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+}
+
+impl<K, T> ReadOnlyTable<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Get a record from the table.
+    ///
+    /// The `key` of the record to get.
+    ///
+    /// Returns a `MooResult` with the value, or `NotFound` if there is no record with the key.
+    pub fn get<Q>(&self, key: &Q) -> MooResult<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        self.table.record_get();
+
+        match self.table.live_value(key) {
+            Ok(value) => {
+                self.table.debugger.log(
+                    DebugLevel::Info,
+                    format!("Found record with key: {:?}", key),
+                );
+
+                Ok(value.clone())
+            }
+            Err(err) => {
+                self.table.debugger.log(
+                    DebugLevel::Warning,
+                    format!("No record found with key: {:?}", key),
+                );
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Get multiple records from the table at once.
+    ///
+    /// The `keys` of the records to get. The keys are treated as a set, so a duplicated key only returns its record once.
+    ///
+    /// Returns a `MooResult` with the records in table order, or `NotFound` if none of the keys were found.
+    pub fn get_many<Q>(&self, keys: &[&Q]) -> MooResult<MooRecords<T, K>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+    {
+        self.table.record_get();
+
+        let keys = dedupe_keys(keys);
+        let wanted: HashSet<&Q> = keys.iter().copied().collect();
+
+        let records: MooRecords<T, K> = self
+            .table
+            .iter()
+            .filter(|record| record.is_live() && wanted.contains(record.key.borrow()))
+            .cloned()
+            .collect();
+
+        if records.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No records found with keys: {:?}", keys),
+            });
+        }
+
+        Ok(records)
+    }
+
+    /// Get all the records from the table.
+    ///
+    /// Returns a `MooResult` with the records, or `NotFound` if the table is empty.
+    pub fn get_all(&self) -> MooResult<MooRecords<T, K>> {
+        let records: MooRecords<T, K> = self
+            .table
+            .iter()
+            .filter(|record| record.is_live())
+            .cloned()
+            .collect();
+
+        if records.is_empty() {
+            return Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: "No records found in the table.".to_string(),
+            });
+        }
+
+        Ok(records)
+    }
+
+    /// Find all records whose value matches the `predicate`.
+    ///
+    /// Returns a `MooResult` with the matching records, an empty vector if nothing matches.
+    pub fn find<F>(&self, predicate: F) -> MooResult<MooRecords<T, K>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.table.find(predicate)
+    }
+
+    /// Whether a record with the `key` is in the table.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.table.contains_key(key)
+    }
+
+    /// The number of records in the table.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether the table has no records.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+}
+
+/// A mutable borrow of the value of a record, which saves the table when it goes out of scope.
+///
+/// Create one with `MooTable::entry`. The guard derefs to the value of the record.
+#[derive(Debug)]
+pub struct EntryGuard<'a, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// The table the record is in.
+    table: &'a mut MooTable<K, T>,
+    /// The position of the record in the table.
+    index: usize,
+    /// Whether the value was borrowed mutably, so the table needs to be saved.
+    changed: bool,
+    /// Whether the change was already saved by `commit`.
+    committed: bool,
+}
+
+impl<K, T> EntryGuard<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Save the change to the value now instead of when the guard is dropped.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn commit(mut self) -> MooResult<()> {
+        self.committed = true;
+        self.save_change()
+    }
+
+    /// Updates the indexes of the changed record, saves the table and notifies the subscribers.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn save_change(&mut self) -> MooResult<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let table = &mut *self.table;
+        let key = table.records[self.index].key.clone();
+        let value = table.records[self.index].value.clone();
+
+        // The old value is gone, so the numeric indexes are rebuilt instead of moving a single entry.
+        if !table.numeric_indexes.is_empty() {
+            table.rebuild_indexes();
+        }
+
+        table.mark_changed(&key);
+        table.save()?;
+
+        table.debugger.log(
+            DebugLevel::Info,
+            format!("Modified record with key: {:?}", key),
+        );
+
+        table.notify(vec![ChangeEvent::Updated { key, value }])
+    }
+}
+
+impl<K, T> Deref for EntryGuard<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.table.records[self.index].value
+    }
+}
+
+impl<K, T> DerefMut for EntryGuard<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed = true;
+        &mut self.table.records[self.index].value
+    }
+}
+
+impl<K, T> Drop for EntryGuard<'_, K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Saves the change to the value, unless it was already saved by `commit`.
+    ///
+    /// Drop can't return an error, so a failed save is logged instead.
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        if let Err(err) = self.save_change() {
+            self.table.debugger.log(
+                DebugLevel::Error,
+                format!(
+                    "Failed to save entry of table: {}: {}",
+                    self.table.name, err.message
+                ),
+            );
+        }
+    }
+}
+
+/// An iterator over the records of a table in table order, which skips soft deleted records.
+///
+/// Create one with `MooTable::iter` or by looping over a `&MooTable`.
+#[derive(Debug, Clone)]
+pub struct RecordIter<'a, K, T>
+where
+    T: Serialize,
+{
+    /// The records of the table, including the soft deleted ones.
+    records: std::slice::Iter<'a, MooRecord<T, K>>,
+}
+
+impl<'a, K, T> Iterator for RecordIter<'a, K, T>
+where
+    T: Serialize,
+{
+    type Item = &'a MooRecord<T, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.find(|record| !record.deleted)
+    }
+}
+
+impl<'a, K, T> IntoIterator for &'a MooTable<K, T>
+where
+    K: MooKey,
+    T: Clone + Serialize + DeserializeOwned,
+{
+    type Item = &'a MooRecord<T, K>;
+    type IntoIter = RecordIter<'a, K, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::Arc,
+        thread,
+        time::Duration,
+        vec,
+    };
+
+    use super::{MooClient, MooStringTable, ReadOnlyTable};
+    use crate::types::{
+        BatchReport, Compression, ConflictPolicy, ImportMode, RunMode, SerializationFormat,
+        StorageTypes,
+    };
+    use crate::{ChangeEvent, Configuration, MooErrorCodes, MooRecord, MooRecords};
+
+    #[test]
+    fn test_delete_many() {
+        let mut db = MooClient::<String>::new(
+            "test_delete_many",
+            None,
+            Some(Configuration {
+                db_dir: PathBuf::from("db/moo"),
+                debug_mode: true,
+                debug_level: None,
+                ..Configuration::default()
+            }),
+        )
+        .unwrap();
+
+        db.reset_table("test_delete_many").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        for i in 0..50 {
+            let data = format!("Example Person {}", i);
+
+            people.insert(i.to_string(), data).unwrap();
+        }
+
+        assert_eq!(people.records.len(), 50);
+
+        people.delete_many(vec!["1", "2", "3"]).unwrap();
+
+        assert_eq!(people.records.len(), 47);
+
+        let u = vec![
+            MooRecord {
+                key: "4".to_string(),
+                value: "Example Person 4 updated".to_string(),
+                expires_at: None,
+                deleted: false,
+            },
+            MooRecord {
+                key: "5".to_string(),
+                value: "Example Person 5 updated".to_string(),
+                expires_at: None,
+                deleted: false,
+            },
             MooRecord {
+                key: "6".to_string(),
+                value: "Example Person 6 updated".to_string(),
+                expires_at: None,
+                deleted: false,
+            },
+        ];
+
+        people.update_many(u).unwrap();
+
+        assert_eq!(people.get("4").unwrap(), "Example Person 4 updated");
+
+        let found = people.get_many(vec!["7", "7", "8"]).unwrap();
+
+        assert_eq!(found.len(), 2);
+
+        let (found, missing) = people.get_many_checked(&["8", "1", "9", "1"]).unwrap();
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].key, "8");
+        assert_eq!(missing, vec!["1".to_string()]);
+
+        people.delete_many(vec!["7", "7"]).unwrap();
+
+        assert_eq!(people.records.len(), 46);
+    }
+
+    #[test]
+    fn test_range_by_index() {
+        let mut db = MooClient::<f64>::new("test_range_by_index", None, None).unwrap();
+
+        db.reset_table("test_range_by_index").unwrap();
+
+        let table = db.get_table().unwrap();
+        let mut balances = table.write().unwrap();
+
+        balances.create_numeric_index("balance", |b| *b).unwrap();
+
+        for i in 0..20 {
+            balances.insert(i.to_string(), i as f64 * 10.0).unwrap();
+        }
+
+        let found = balances.range_by_index("balance", 10.0..=40.0).unwrap();
+
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0].key, "1");
+
+        balances.update("2", 500.0).unwrap();
+        balances.delete("3").unwrap();
+
+        let found = balances.range_by_index("balance", 10.0..=40.0).unwrap();
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            balances.range_by_index("balance", 500.0..).unwrap()[0].key,
+            "2"
+        );
+
+        drop(balances);
+
+        // Range queries only need a read lock on a shared table.
+        let balances = table.read().unwrap();
+
+        assert_eq!(
+            balances.range_by_index("balance", ..=10.0).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_open_or_init() {
+        let seed = |table: &mut MooStringTable<String>| table.insert("1", "Seeded".to_string());
+
+        let mut db =
+            MooClient::<String>::open_or_init("test_open_or_init", None, None, seed).unwrap();
+
+        db.delete_table("test_open_or_init").unwrap();
+
+        let mut db =
+            MooClient::<String>::open_or_init("test_open_or_init", None, None, seed).unwrap();
+
+        assert_eq!(
+            db.get_table().unwrap().write().unwrap().get("1").unwrap(),
+            "Seeded"
+        );
+
+        db.table
+            .write()
+            .unwrap()
+            .update("1", "Changed".to_string())
+            .unwrap();
+
+        let mut db =
+            MooClient::<String>::open_or_init("test_open_or_init", None, None, seed).unwrap();
+
+        assert_eq!(
+            db.get_table().unwrap().write().unwrap().get("1").unwrap(),
+            "Changed"
+        );
+    }
+
+    #[cfg(feature = "arc-values")]
+    #[test]
+    fn test_arc_values() {
+        use super::ArcMooClient;
+        use std::sync::Arc;
+
+        let mut db = ArcMooClient::<String>::new("test_arc_values", None, None).unwrap();
+
+        db.reset_table("test_arc_values").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", Arc::new("John".to_string())).unwrap();
+
+        let first = people.get("1").unwrap();
+        let second = people.get("1").unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn test_discard_changes() {
+        let mut db = MooClient::<String>::new("test_discard_changes", None, None).unwrap();
+
+        db.reset_table("test_discard_changes").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+
+        assert!(!people.is_dirty());
+
+        people.records.push(MooRecord {
+            key: "2".to_string(),
+            value: "Jane".to_string(),
+            expires_at: None,
+            deleted: false,
+        });
+
+        people.discard_changes().unwrap();
+
+        assert_eq!(people.records.len(), 1);
+        assert_eq!(people.get("1").unwrap(), "John");
+    }
+
+    #[test]
+    fn test_conflict_policy() {
+        let config = Configuration {
+            conflict_policy: ConflictPolicy::Abort,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<String>::new("test_conflict_policy", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_conflict_policy").unwrap();
+
+        let first = db.get_table().unwrap();
+        let mut first = first.write().unwrap();
+        let second = MooClient::<String>::new("test_conflict_policy", None, Some(config))
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let mut second = second.write().unwrap();
+
+        first.insert("1", "John".to_string()).unwrap();
+
+        assert!(second.insert("2", "Jane".to_string()).is_err());
+        assert!(!second.is_dirty());
+        assert!(!second.contains_key("2"));
+
+        second.discard_changes().unwrap();
+        second.config.conflict_policy = ConflictPolicy::MergeNewest;
+        first.config.conflict_policy = ConflictPolicy::MergeNewest;
+
+        first.insert("3", "Bob".to_string()).unwrap();
+        second.update("1", "Johnny".to_string()).unwrap();
+
+        assert_eq!(second.records.len(), 2);
+        assert_eq!(second.get("1").unwrap(), "Johnny");
+        assert_eq!(second.get("3").unwrap(), "Bob");
+    }
+
+    #[test]
+    fn test_modify_each() {
+        let mut db = MooClient::<i32>::new("test_modify_each", None, None).unwrap();
+
+        db.reset_table("test_modify_each").unwrap();
+
+        let numbers = db.get_table().unwrap();
+        let mut numbers = numbers.write().unwrap();
+
+        for i in 0..10 {
+            numbers.insert(i.to_string(), i).unwrap();
+        }
+
+        let changed = numbers
+            .modify_each(|_, value| {
+                if *value % 2 == 0 {
+                    *value *= 10;
+                    return true;
+                }
+
+                false
+            })
+            .unwrap();
+
+        assert_eq!(changed, 5);
+        assert_eq!(numbers.get("4").unwrap(), 40);
+        assert_eq!(numbers.get("5").unwrap(), 5);
+        assert!(!numbers.is_dirty());
+    }
+
+    #[test]
+    fn test_stats_all() {
+        let dir = Some(Path::new("db/test_stats_all"));
+
+        let mut other = MooClient::<String>::new("other", dir, None).unwrap();
+        other.reset_table("other").unwrap();
+        other
+            .table
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        let mut db = MooClient::<String>::new("main", dir, None).unwrap();
+        db.reset_table("main").unwrap();
+
+        let stats = db.stats_all().unwrap();
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["other"].records, 1);
+        assert_eq!(stats["main"].records, 0);
+        assert!(stats["other"].size > 0);
+    }
+
+    #[test]
+    fn test_rename_many() {
+        let mut db = MooClient::<String>::new("test_rename_many", None, None).unwrap();
+
+        db.reset_table("test_rename_many").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("a", "John".to_string()).unwrap();
+        people.insert("b", "Jane".to_string()).unwrap();
+        people.insert("c", "Bob".to_string()).unwrap();
+
+        assert!(people.rename_many(&[("a", "x"), ("b", "c")]).is_err());
+        assert!(people.rename_many(&[("a", "x"), ("b", "x")]).is_err());
+        assert!(people.rename_many(&[("a", "x"), ("missing", "y")]).is_err());
+        assert_eq!(people.get("a").unwrap(), "John");
+
+        assert_eq!(people.rename_many(&[("a", "b"), ("b", "a")]).unwrap(), 2);
+        assert_eq!(people.get("a").unwrap(), "Jane");
+        assert_eq!(people.get("b").unwrap(), "John");
+
+        assert!(people.rename_key("a", "c").is_err());
+        assert!(people.rename_key("missing", "d").is_err());
+
+        people.rename_key("a", "d").unwrap();
+
+        assert_eq!(people.get("d").unwrap(), "Jane");
+        assert!(!people.contains_key("a"));
+        assert_eq!(people.keys().collect::<Vec<_>>(), vec!["b", "d", "c"]);
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let config = Configuration {
+            audit_log: true,
+            ..Configuration::default()
+        };
+
+        let dir = Some(Path::new("db/test_audit_log"));
+        let _ = fs::remove_file("db/test_audit_log/people.audit.jsonl");
+
+        let mut db = MooClient::<String>::new("people", dir, Some(config)).unwrap();
+
+        db.reset_table("people").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.update("1", "Jane".to_string()).unwrap();
+        people.delete("1").unwrap();
+
+        let audit = fs::read_to_string("db/test_audit_log/people.audit.jsonl").unwrap();
+        let ops: Vec<serde_json::Value> = audit
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(ops.len(), 3);
+        assert_eq!(ops[0]["op"], "insert");
+        assert_eq!(ops[1]["value"], "Jane");
+        assert_eq!(ops[2]["op"], "delete");
+    }
+
+    #[test]
+    fn test_multiple_tables() {
+        let dir = Some(Path::new("db/test_multiple_tables"));
+
+        let mut db = MooClient::<String>::new("accounts", dir, None).unwrap();
+
+        for name in ["sessions", "audit"] {
+            let _ = db.delete_table(name);
+        }
+
+        let sessions = db.create_table("sessions").unwrap();
+        let mut sessions = sessions.write().unwrap();
+        let audit = db.create_table("audit").unwrap();
+        let mut audit = audit.write().unwrap();
+
+        assert!(db.create_table("sessions").is_err());
+
+        sessions.insert("1", "Session".to_string()).unwrap();
+        audit.insert("1", "Audit".to_string()).unwrap();
+
+        assert_eq!(
+            db.list_tables().unwrap(),
+            vec!["accounts", "audit", "sessions"]
+        );
+
+        drop(audit);
+
+        db.reset_table("audit").unwrap();
+
+        let mut reopened = MooClient::<String>::new("accounts", dir, None).unwrap();
+
+        assert_eq!(
+            reopened
+                .open_table("sessions")
+                .unwrap()
+                .write()
+                .unwrap()
+                .get("1")
+                .unwrap(),
+            "Session"
+        );
+        assert!(reopened
+            .open_table("audit")
+            .unwrap()
+            .write()
+            .unwrap()
+            .records
+            .is_empty());
+        assert!(reopened.open_table("missing").is_err());
+
+        reopened.delete_table("sessions").unwrap();
+
+        assert_eq!(reopened.list_tables().unwrap(), vec!["accounts", "audit"]);
+    }
+
+    #[test]
+    fn test_numeric_keys() {
+        let mut db = MooClient::<String, u64>::new("test_numeric_keys", None, None).unwrap();
+
+        db.reset_table("test_numeric_keys").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert(1u64, "John".to_string()).unwrap();
+        people.insert(2u64, "Jane".to_string()).unwrap();
+        people.rename_many(&[(&2, &3)]).unwrap();
+
+        assert_eq!(people.get(&1).unwrap(), "John");
+        assert_eq!(people.get(&3).unwrap(), "Jane");
+        assert!(people.get(&2).is_err());
+
+        let contents = fs::read_to_string("db/moo/test_numeric_keys.json").unwrap();
+        let (_, records) = contents.split_once('\n').unwrap();
+        let stored: serde_json::Value = serde_json::from_str(records).unwrap();
+
+        assert_eq!(stored[0]["key"], 1);
+        assert_eq!(stored[1]["key"], 3);
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let dir = Some(Path::new("db/test_memory_storage"));
+
+        let mut first = MooClient::<String>::new("cache", dir, Some(config.clone())).unwrap();
+        let second = MooClient::<String>::new("cache", dir, Some(config)).unwrap();
+
+        first
+            .table
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        assert!(!first.table.write().unwrap().is_dirty());
+        assert!(second.table.write().unwrap().get("1").is_err());
+        assert!(!Path::new("db/test_memory_storage").exists());
+
+        first
+            .create_table("sessions")
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", "Session".to_string())
+            .unwrap();
+
+        assert_eq!(first.list_tables().unwrap(), vec!["cache", "sessions"]);
+        assert_eq!(first.stats_all().unwrap()["cache"].records, 1);
+
+        first.reset_table("cache").unwrap();
+        first.delete_table("sessions").unwrap();
+
+        assert!(first.table.write().unwrap().records.is_empty());
+        assert_eq!(first.list_tables().unwrap(), vec!["cache"]);
+        assert!(!Path::new("db/test_memory_storage").exists());
+    }
+
+    #[test]
+    fn test_atomic_save() {
+        let mut db = MooClient::<String>::new("test_atomic_save", None, None).unwrap();
+
+        db.reset_table("test_atomic_save").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+
+        // A save killed halfway through only leaves a partial temporary file behind.
+        fs::write("db/moo/test_atomic_save.json.tmp", "[{\"key\":\"2\",\"val").unwrap();
+
+        let reopened = MooClient::<String>::new("test_atomic_save", None, None).unwrap();
+
+        assert_eq!(reopened.table.write().unwrap().get("1").unwrap(), "John");
+
+        reopened
+            .table
+            .write()
+            .unwrap()
+            .insert("2", "Jane".to_string())
+            .unwrap();
+        people.discard_changes().unwrap();
+
+        assert_eq!(people.get("2").unwrap(), "Jane");
+        assert!(!Path::new("db/moo/test_atomic_save.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_key_index() {
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let db = MooClient::<u64, u64>::new("test_key_index", None, Some(config)).unwrap();
+
+        for i in 0..50_000u64 {
+            db.table.write().unwrap().insert(i, i * 2).unwrap();
+        }
+
+        for i in 0..50_000u64 {
+            assert_eq!(db.table.write().unwrap().get(&i).unwrap(), i * 2);
+        }
+
+        db.table.write().unwrap().delete(&10).unwrap();
+        db.table
+            .write()
+            .unwrap()
+            .delete_many(vec![&0, &49_999])
+            .unwrap();
+        db.table
+            .write()
+            .unwrap()
+            .rename_many(&[(&1, &2), (&2, &1)])
+            .unwrap();
+
+        assert!(db.table.write().unwrap().get(&10).is_err());
+        assert!(db.table.write().unwrap().insert(11u64, 0).is_err());
+        assert_eq!(db.table.write().unwrap().get(&11).unwrap(), 22);
+        assert_eq!(db.table.write().unwrap().get(&1).unwrap(), 4);
+        assert_eq!(db.table.write().unwrap().get(&49_998).unwrap(), 99_996);
+    }
+
+    #[test]
+    fn test_upsert() {
+        let mut db = MooClient::<String>::new("test_upsert", None, None).unwrap();
+
+        db.reset_table("test_upsert").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+        let events = people.subscribe();
+
+        people.upsert("1", "John".to_string()).unwrap();
+        people.upsert("1", "Johnny".to_string()).unwrap();
+
+        let report = people
+            .upsert_many(vec![
+                MooRecord {
+                    key: "1".to_string(),
+                    value: "Jack".to_string(),
+                    expires_at: None,
+                    deleted: false,
+                },
+                MooRecord {
+                    key: "2".to_string(),
+                    value: "Jane".to_string(),
+                    expires_at: None,
+                    deleted: false,
+                },
+            ])
+            .unwrap();
+
+        assert_eq!(
+            report,
+            BatchReport {
+                inserted: 1,
+                updated: 1
+            }
+        );
+
+        assert!(people.upsert_many(vec![]).is_err());
+        assert_eq!(people.records.len(), 2);
+        assert_eq!(people.get("1").unwrap(), "Jack");
+        assert_eq!(people.get("2").unwrap(), "Jane");
+        assert!(matches!(
+            events.try_iter().collect::<Vec<_>>().as_slice(),
+            [
+                ChangeEvent::Inserted { .. },
+                ChangeEvent::Updated { .. },
+                ChangeEvent::Updated { .. },
+                ChangeEvent::Inserted { .. },
+            ]
+        ));
+
+        let reopened = MooClient::<String>::new("test_upsert", None, None).unwrap();
+
+        assert_eq!(reopened.table.write().unwrap().get("1").unwrap(), "Jack");
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut db = MooClient::<String>::new("test_contains_key", None, None).unwrap();
+
+        db.reset_table("test_contains_key").unwrap();
+
+        assert!(db.table.write().unwrap().is_empty());
+        assert_eq!(db.table.write().unwrap().count(), 0);
+
+        db.table
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        let people = db.table.read().unwrap();
+
+        assert!(people.contains_key("1"));
+        assert!(!people.contains_key("2"));
+        assert_eq!(people.len(), 1);
+        assert!(!people.is_empty());
+
+        assert_eq!(people.which_exist(&["2", "1", "1"]), vec!["1"]);
+        assert_eq!(people.which_missing(&["2", "1", "3"]), vec!["2", "3"]);
+    }
+
+    #[test]
+    fn test_serialization_formats() {
+        let dir = Some(Path::new("db/test_serialization_formats"));
+        let _ = fs::remove_dir_all("db/test_serialization_formats");
+
+        for (name, format) in [
+            ("bincode", SerializationFormat::Bincode),
+            ("msgpack", SerializationFormat::MessagePack),
+        ] {
+            let config = Configuration {
+                format,
+                ..Configuration::default()
+            };
+
+            let db = MooClient::<String, u64>::new(name, dir, Some(config)).unwrap();
+
+            db.table
+                .write()
+                .unwrap()
+                .insert(1u64, "John".to_string())
+                .unwrap();
+            db.table
+                .write()
+                .unwrap()
+                .insert(2u64, "Jane".to_string())
+                .unwrap();
+
+            let file_path = format!(
+                "db/test_serialization_formats/{}.{}",
+                name,
+                format.extension()
+            );
+
+            assert!(Path::new(&file_path).exists());
+
+            // The existing file is detected even though JSON is configured.
+            let reopened = MooClient::<String, u64>::new(name, dir, None).unwrap();
+
+            assert_eq!(reopened.table.write().unwrap().get(&2).unwrap(), "Jane");
+
+            reopened.table.write().unwrap().delete(&1).unwrap();
+
+            assert_eq!(reopened.stats_all().unwrap()[name].records, 1);
+        }
+
+        let db = MooClient::<String, u64>::new("json", dir, None).unwrap();
+
+        assert!(Path::new("db/test_serialization_formats/json.json").exists());
+        assert_eq!(
+            db.list_tables().unwrap(),
+            vec!["bincode", "json", "msgpack"]
+        );
+        assert_eq!(db.stats_all().unwrap()["msgpack"].records, 1);
+    }
+
+    #[test]
+    fn test_gzip_compression() {
+        let dir = Some(Path::new("db/test_gzip_compression"));
+        let _ = fs::remove_dir_all("db/test_gzip_compression");
+
+        let records: Vec<MooRecord<String>> = (0..10_000)
+            .map(|i| MooRecord {
+                key: i.to_string(),
+                value: format!("Example Person {} from the example street", i),
+                expires_at: None,
+                deleted: false,
+            })
+            .collect();
+
+        let config = Configuration {
+            compression: Some(Compression::Gzip),
+            ..Configuration::default()
+        };
+
+        let compressed = MooClient::<String>::new("compressed", dir, Some(config)).unwrap();
+        let plain = MooClient::<String>::new("plain", dir, None).unwrap();
+
+        compressed
+            .table
+            .write()
+            .unwrap()
+            .insert_many(records.clone())
+            .unwrap();
+        plain.table.write().unwrap().insert_many(records).unwrap();
+
+        let compressed_size = fs::metadata("db/test_gzip_compression/compressed.json.gz")
+            .unwrap()
+            .len();
+        let plain_size = fs::metadata("db/test_gzip_compression/plain.json")
+            .unwrap()
+            .len();
+
+        assert!(compressed_size * 4 < plain_size);
+
+        // The compression is detected from the extension without configuring it.
+        let reopened = MooClient::<String>::new("compressed", dir, None).unwrap();
+
+        assert_eq!(reopened.table.write().unwrap().len(), 10_000);
+        assert_eq!(
+            reopened.table.write().unwrap().get("9999").unwrap(),
+            "Example Person 9999 from the example street"
+        );
+        assert_eq!(reopened.list_tables().unwrap(), vec!["compressed", "plain"]);
+        assert_eq!(plain.stats_all().unwrap()["compressed"].records, 10_000);
+    }
+
+    #[test]
+    fn test_ttl() {
+        fs::create_dir_all("db/test_ttl").unwrap();
+        fs::write(
+            "db/test_ttl/sessions.json",
+            r#"[{"key":"old","value":"Saved before expiry existed"}]"#,
+        )
+        .unwrap();
+
+        let mut db =
+            MooClient::<String>::new("sessions", Some(Path::new("db/test_ttl")), None).unwrap();
+        let sessions = db.get_table().unwrap();
+        let mut sessions = sessions.write().unwrap();
+
+        assert_eq!(sessions.get("old").unwrap(), "Saved before expiry existed");
+
+        sessions
+            .insert_with_ttl("1", "Expired".to_string(), Duration::ZERO)
+            .unwrap();
+        sessions
+            .insert_with_ttl("2", "Expired".to_string(), Duration::ZERO)
+            .unwrap();
+        sessions
+            .insert_with_ttl("3", "Alive".to_string(), Duration::from_secs(3600))
+            .unwrap();
+
+        assert!(sessions.get("1").is_err());
+        assert!(!sessions.contains_key("1"));
+        assert_eq!(sessions.get("3").unwrap(), "Alive");
+
+        sessions.insert("2", "Replaced".to_string()).unwrap();
+
+        assert_eq!(sessions.get("2").unwrap(), "Replaced");
+
+        sessions
+            .insert_with_ttl("4", "Expired".to_string(), Duration::ZERO)
+            .unwrap();
+
+        assert_eq!(
+            sessions
+                .update("4", "Revived".to_string())
+                .unwrap_err()
+                .code,
+            MooErrorCodes::NotFound
+        );
+
+        sessions
+            .update_many(vec![MooRecord {
+                key: "4".to_string(),
+                value: "Revived".to_string(),
+                expires_at: None,
+                deleted: false,
+            }])
+            .unwrap();
+
+        assert!(!sessions.contains_key("4"));
+        assert_eq!(sessions.purge_expired().unwrap(), 1);
+        assert_eq!(sessions.purge_expired().unwrap(), 0);
+        assert_eq!(sessions.len(), 3);
+
+        let contents = fs::read_to_string("db/test_ttl/sessions.json").unwrap();
+        let (_, records) = contents.split_once('\n').unwrap();
+        let stored: serde_json::Value = serde_json::from_str(records).unwrap();
+
+        assert!(stored[0]["expires_at"].is_null());
+        assert!(stored[1]["expires_at"].is_i64());
+    }
+
+    #[test]
+    fn test_save_as() {
+        let dir = Path::new("db/test_save_as");
+
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        let config = Configuration::builder().soft_delete(true).build();
+        let mut db = MooClient::<u32>::new("scores", Some(dir), Some(config)).unwrap();
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 10).unwrap();
+        scores.insert("2", 20).unwrap();
+        scores.insert("3", 30).unwrap();
+        scores.insert_with_ttl("4", 40, Duration::ZERO).unwrap();
+        scores.delete("2").unwrap();
+
+        let copy_path = dir.join("copy.bin.gz");
+
+        scores
+            .save_as(
+                &copy_path,
+                SerializationFormat::Bincode,
+                Some(Compression::Gzip),
+            )
+            .unwrap();
+
+        assert!(copy_path.exists());
+        assert!(!dir.join("copy.bin.gz.tmp").exists());
+
+        let mut copy_db = MooClient::<u32>::new("copy", Some(dir), None).unwrap();
+        let copy = copy_db.get_table().unwrap();
+        let mut copy = copy.write().unwrap();
+
+        assert_eq!(copy.all().len(), 2);
+        assert_eq!(copy.get("1").unwrap(), 10);
+        assert_eq!(copy.get("3").unwrap(), 30);
+        assert!(copy.get("2").is_err());
+        assert!(copy.get("4").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let mut db = MooClient::<f64>::new("test_find", None, None).unwrap();
+
+        db.reset_table("test_find").unwrap();
+
+        let balances = db.get_table().unwrap();
+        let mut balances = balances.write().unwrap();
+
+        for i in 0..10 {
+            balances.insert(i.to_string(), i as f64 * 500.0).unwrap();
+        }
+
+        let rich = balances.find(|balance| *balance > 1000.0).unwrap();
+
+        assert_eq!(rich.len(), 7);
+        assert_eq!(rich[0].key, "3");
+        assert!(balances.find(|balance| *balance < 0.0).unwrap().is_empty());
+        assert_eq!(
+            balances.find_one(|balance| *balance > 1000.0).unwrap().key,
+            "3"
+        );
+        assert!(balances.find_one(|balance| *balance < 0.0).is_none());
+        assert_eq!(balances.count_where(|balance| *balance >= 1000.0), 8);
+    }
+
+    #[test]
+    fn test_scan_prefix() {
+        let mut db = MooClient::<String>::new("test_scan_prefix", None, None).unwrap();
+
+        db.reset_table("test_scan_prefix").unwrap();
+
+        let settings = db.get_table().unwrap();
+        let mut settings = settings.write().unwrap();
+
+        settings
+            .insert("user:1:profile", "John".to_string())
+            .unwrap();
+        settings
+            .insert("user:2:profile", "Jane".to_string())
+            .unwrap();
+        settings.insert("user:1:theme", "dark".to_string()).unwrap();
+
+        let user = settings.scan_prefix("user:1:").unwrap();
+
+        assert_eq!(user.len(), 2);
+        assert_eq!(user[0].key, "user:1:profile");
+        assert_eq!(user[1].key, "user:1:theme");
+        assert!(settings.scan_prefix("group:").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut db = MooClient::<i32>::new("test_iter", None, None).unwrap();
+
+        db.reset_table("test_iter").unwrap();
+
+        for i in 0..5 {
+            db.table.write().unwrap().insert(i.to_string(), i).unwrap();
+        }
+
+        let numbers = db.table.read().unwrap();
+        let mut sum = 0;
+
+        for record in &*numbers {
+            sum += record.value;
+            assert_eq!(numbers.name, "test_iter");
+        }
+
+        assert_eq!(sum, 10);
+        assert_eq!(numbers.iter().filter(|record| record.value > 2).count(), 2);
+    }
+
+    #[test]
+    fn test_keys_values() {
+        let mut db = MooClient::<f64>::new("test_keys_values", None, None).unwrap();
+
+        db.reset_table("test_keys_values").unwrap();
+
+        let balances = db.get_table().unwrap();
+        let mut balances = balances.write().unwrap();
+
+        balances.insert("b", 20.0).unwrap();
+        balances.insert("a", 100.0).unwrap();
+
+        assert_eq!(balances.keys().collect::<Vec<_>>(), vec!["b", "a"]);
+        assert_eq!(balances.values().sum::<f64>(), 120.0);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut db = MooClient::<i32>::new("test_get_or_insert_with", None, None).unwrap();
+
+        db.reset_table("test_get_or_insert_with").unwrap();
+
+        let cache = db.get_table().unwrap();
+        let mut cache = cache.write().unwrap();
+        let mut calls = 0;
+
+        for _ in 0..3 {
+            let value = cache
+                .get_or_insert_with("answer", || {
+                    calls += 1;
+                    42
+                })
+                .unwrap();
+
+            assert_eq!(value, 42);
+        }
+
+        assert_eq!(calls, 1);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_get_page() {
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<u32>::new("test_get_page", None, Some(config)).unwrap();
+        let numbers = db.get_table().unwrap();
+        let mut numbers = numbers.write().unwrap();
+
+        for i in 0..25 {
+            numbers.insert(i.to_string(), i).unwrap();
+        }
+
+        assert_eq!(numbers.page_count(10), 3);
+
+        let pages: Vec<MooRecords<u32>> = (0..numbers.page_count(10))
+            .map(|page| numbers.get_page(page * 10, 10).unwrap())
+            .collect();
+
+        assert_eq!(pages[0].len(), 10);
+        assert_eq!(pages[2].len(), 5);
+        assert_eq!(pages[1][0].value, 10);
+        assert!(numbers.get_page(30, 10).unwrap().is_empty());
+        assert!(numbers.get_page(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut db = MooClient::<i64>::new("test_increment", None, None).unwrap();
+
+        db.reset_table("test_increment").unwrap();
+
+        let counters = db.get_table().unwrap();
+        let mut counters = counters.write().unwrap();
+
+        counters.insert("visits", 10).unwrap();
+
+        assert_eq!(counters.increment("visits", 5).unwrap(), 15);
+        assert_eq!(counters.decrement("visits", 20).unwrap(), -5);
+        assert_eq!(counters.get("visits").unwrap(), -5);
+        assert!(counters.increment("missing", 1).is_err());
+
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<u8>::new("test_increment_bounds", None, Some(config)).unwrap();
+        let stock = db.get_table().unwrap();
+        let mut stock = stock.write().unwrap();
+
+        stock.insert("apples", 250).unwrap();
+
+        assert_eq!(stock.increment("apples", 5).unwrap(), 255);
+        assert_eq!(
+            stock.increment("apples", 1).unwrap_err().code,
+            MooErrorCodes::Warn
+        );
+        assert_eq!(stock.decrement("apples", 255).unwrap(), 0);
+        assert_eq!(
+            stock.decrement("apples", 1).unwrap_err().code,
+            MooErrorCodes::Warn
+        );
+        assert_eq!(stock.get("apples").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_update_if() {
+        let mut db = MooClient::<f64>::new("test_update_if", None, None).unwrap();
+
+        db.reset_table("test_update_if").unwrap();
+
+        let balances = db.get_table().unwrap();
+        let mut balances = balances.write().unwrap();
+
+        balances.insert("1", 100.0).unwrap();
+
+        assert!(balances
+            .update_if("1", |balance| *balance >= 80.0, 20.0)
+            .unwrap());
+        assert!(!balances
+            .update_if("1", |balance| *balance >= 80.0, -60.0)
+            .unwrap());
+        assert_eq!(balances.get("1").unwrap(), 20.0);
+        assert!(balances.update_if("2", |_| true, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_batch() {
+        let mut db = MooClient::<String>::new("test_batch", None, None).unwrap();
+
+        db.reset_table("test_batch").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.insert("2", "Jane".to_string()).unwrap();
+
+        people
+            .batch()
+            .insert("3", "Jack".to_string())
+            .update("1", "Johnny".to_string())
+            .delete("2")
+            .commit()
+            .unwrap();
+
+        assert_eq!(people.get("1").unwrap(), "Johnny");
+        assert_eq!(people.get("3").unwrap(), "Jack");
+        assert!(!people.contains_key("2"));
+
+        // A failing operation leaves the table untouched.
+        let result = people
+            .batch()
+            .insert("4", "Jill".to_string())
+            .delete("1")
+            .update("5", "Joe".to_string())
+            .commit();
+
+        assert!(result.is_err());
+        assert!(!people.contains_key("4"));
+        assert_eq!(people.get("1").unwrap(), "Johnny");
+        assert_eq!(people.len(), 2);
+
+        let on_disk = MooClient::<String>::new("test_batch", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let on_disk = on_disk.write().unwrap();
+
+        assert_eq!(on_disk.len(), 2);
+        assert!(on_disk.contains_key("3"));
+    }
+
+    #[test]
+    fn test_save_rollback() {
+        let config = Configuration {
+            conflict_policy: ConflictPolicy::Abort,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<String>::new("test_save_rollback", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_save_rollback").unwrap();
+
+        let first = db.get_table().unwrap();
+        let mut first = first.write().unwrap();
+
+        first.insert("1", "John".to_string()).unwrap();
+        first.insert("2", "Jane".to_string()).unwrap();
+        first
+            .insert_with_ttl("8", "Jean".to_string(), Duration::ZERO)
+            .unwrap();
+
+        let second = MooClient::<String>::new("test_save_rollback", None, Some(config))
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let mut second = second.write().unwrap();
+
+        // Saving through the first handle makes every save of the second one fail.
+        first.insert("3", "Jack".to_string()).unwrap();
+
+        assert!(second.insert("4", "Jill".to_string()).is_err());
+        assert!(second
+            .insert_many(vec![MooRecord {
                 key: "5".to_string(),
-                value: "Example Person 5 updated".to_string(),
-            },
-            MooRecord {
-                key: "6".to_string(),
-                value: "Example Person 6 updated".to_string(),
-            },
-        ];
+                value: "Joe".to_string(),
+                expires_at: None,
+                deleted: false,
+            }])
+            .is_err());
+        assert!(second
+            .update_many(vec![MooRecord {
+                key: "1".to_string(),
+                value: "Johnny".to_string(),
+                expires_at: None,
+                deleted: false,
+            }])
+            .is_err());
+        assert!(second.delete_many(vec!["1", "2"]).is_err());
+        assert!(second.upsert("1", "Johnny".to_string()).is_err());
+        assert!(second.upsert("6", "Jen".to_string()).is_err());
+        assert!(second
+            .upsert_many(vec![
+                MooRecord {
+                    key: "2".to_string(),
+                    value: "Janet".to_string(),
+                    expires_at: None,
+                    deleted: false,
+                },
+                MooRecord {
+                    key: "7".to_string(),
+                    value: "Jim".to_string(),
+                    expires_at: None,
+                    deleted: false,
+                },
+            ])
+            .is_err());
+        assert!(second.update("1", "Johnny".to_string()).is_err());
+        assert!(second.modify("1", |value| value.push('!')).is_err());
+        assert!(second
+            .modify_each(|_, value| {
+                value.push('!');
+                true
+            })
+            .is_err());
+        assert!(second.delete("1").is_err());
+        assert_ne!(second.get("8").unwrap_err().code, MooErrorCodes::NotFound);
+        assert!(second.purge_expired().is_err());
+        assert!(second.delete_all().is_err());
+
+        assert_eq!(second.records.len(), 3);
+        assert_eq!(second.get("1").unwrap(), "John");
+        assert_eq!(second.get("2").unwrap(), "Jane");
+        assert!(!second.contains_key("4"));
+        assert!(!second.contains_key("5"));
+        assert!(!second.is_dirty());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let mut db = MooClient::<String>::new("test_export_import", None, None).unwrap();
+
+        db.reset_table("test_export_import").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.insert("2", "Jane".to_string()).unwrap();
+
+        let mut backup = Vec::new();
+        people.export(&mut backup).unwrap();
+
+        people.update("1", "Johnny".to_string()).unwrap();
+        people.insert("3", "Jack".to_string()).unwrap();
+
+        assert_eq!(
+            people.import(backup.as_slice(), ImportMode::Merge).unwrap(),
+            2
+        );
+        assert_eq!(people.get("1").unwrap(), "John");
+        assert_eq!(people.len(), 3);
+
+        assert_eq!(
+            people
+                .import(backup.as_slice(), ImportMode::Replace)
+                .unwrap(),
+            2
+        );
+        assert_eq!(people.len(), 2);
+        assert!(!people.contains_key("3"));
+
+        assert!(people
+            .import("not json".as_bytes(), ImportMode::Replace)
+            .is_err());
+        assert_eq!(people.len(), 2);
+    }
+
+    #[test]
+    fn test_soft_delete_round_trip() {
+        let config = Configuration::builder().soft_delete(true).build();
+
+        let mut db =
+            MooClient::<u32>::new("test_soft_delete_round_trip", None, Some(config)).unwrap();
+
+        db.reset_table("test_soft_delete_round_trip").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.insert("2", 2).unwrap();
+        scores.delete("1").unwrap();
+
+        let mut backup = Vec::new();
+        scores.export(&mut backup).unwrap();
+
+        assert!(!String::from_utf8(backup.clone()).unwrap().contains("\"1\""));
+        assert!(!scores.to_json_string(false).unwrap().contains("\"1\""));
+
+        assert_eq!(
+            scores
+                .import(backup.as_slice(), ImportMode::Replace)
+                .unwrap(),
+            1
+        );
+        assert!(scores.get("1").is_err());
+        assert_eq!(scores.len(), 1);
+
+        // A record marked as deleted in the imported JSON stays deleted.
+        let deleted = r#"[{"key":"2","value":2,"deleted":true}]"#;
+
+        scores.from_json_string(deleted, ImportMode::Merge).unwrap();
+
+        assert!(scores.get("2").is_err());
+        assert!(scores.is_empty());
+
+        scores.restore("2").unwrap();
+        scores.delete("2").unwrap();
+        scores.insert("3", 3).unwrap();
+        scores.snapshot("before").unwrap();
+        scores.restore_snapshot("before").unwrap();
+
+        assert!(scores.get("2").is_err());
+        assert_eq!(scores.len(), 1);
+        scores.restore("2").unwrap();
+
+        assert_eq!(scores.get("2").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_export_csv() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        struct Person {
+            name: String,
+            age: u32,
+            tags: Vec<String>,
+        }
+
+        let mut db = MooClient::<Person>::new("test_export_csv", None, None).unwrap();
+
+        db.reset_table("test_export_csv").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people
+            .insert(
+                "1",
+                Person {
+                    name: "Doe, John".to_string(),
+                    age: 30,
+                    tags: vec!["admin".to_string()],
+                },
+            )
+            .unwrap();
+        people
+            .insert(
+                "2",
+                Person {
+                    name: "Jane".to_string(),
+                    age: 25,
+                    tags: Vec::new(),
+                },
+            )
+            .unwrap();
+
+        let mut csv = Vec::new();
+        people.export_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "key,age,name,tags\n1,30,\"Doe, John\",\"[\"\"admin\"\"]\"\n2,25,Jane,[]\n"
+        );
+    }
+
+    #[test]
+    fn test_import_json_map() {
+        let mut db = MooClient::<String>::new("test_import_json_map", None, None).unwrap();
+
+        db.reset_table("test_import_json_map").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        let legacy = r#"{"1": "John", "2": "Jane"}"#;
+
+        assert_eq!(people.import_json_map(legacy.as_bytes()).unwrap(), 2);
+        assert_eq!(people.get("2").unwrap(), "Jane");
+
+        assert!(people.import_json_map(legacy.as_bytes()).is_err());
+        assert!(people.import_json_map(r#"{"3": 3}"#.as_bytes()).is_err());
+        assert_eq!(people.import_json_map("{}".as_bytes()).unwrap(), 0);
+        assert_eq!(people.len(), 2);
+
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<u32, u64>::new("test_import_json_map_u64", None, Some(config)).unwrap();
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        assert_eq!(
+            scores.import_json_map(r#"{"7": 70}"#.as_bytes()).unwrap(),
+            1
+        );
+        assert_eq!(scores.get(&7).unwrap(), 70);
+    }
+
+    #[test]
+    fn test_shared_table() {
+        fn assert_send_sync<S: Send + Sync>() {}
+        assert_send_sync::<MooStringTable<String>>();
+
+        let mut db = MooClient::<usize>::new("test_shared_table", None, None).unwrap();
+
+        db.reset_table("test_shared_table").unwrap();
+
+        let table = db.get_table().unwrap();
+        let mut handles = Vec::new();
+
+        for writer in 0..2 {
+            let table = Arc::clone(&table);
+
+            handles.push(thread::spawn(move || {
+                for i in 0..50 {
+                    let key = format!("{}-{}", writer, i);
+                    table.write().unwrap().insert(key, i).unwrap();
+                }
+            }));
+        }
+
+        for _ in 0..8 {
+            let table = Arc::clone(&table);
+
+            handles.push(thread::spawn(move || {
+                for _ in 0..200 {
+                    let table = table.read().unwrap();
+
+                    assert_eq!(table.keys().count(), table.len());
+                    assert!(table.values().all(|value| *value < 50));
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let table = table.read().unwrap();
+
+        assert_eq!(table.len(), 100);
+        assert!(table.contains_key("0-49"));
+        assert!(table.contains_key("1-49"));
+    }
+
+    #[test]
+    fn test_autosave() {
+        let config = Configuration {
+            autosave: false,
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<String>::new("test_autosave", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_autosave").unwrap();
+
+        let table = db.get_table().unwrap();
+        let mut people = table.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.insert("2", "Jane".to_string()).unwrap();
+
+        let on_disk = || {
+            MooClient::<String>::new("test_autosave", None, Some(config.clone()))
+                .unwrap()
+                .get_table()
+                .unwrap()
+                .write()
+                .unwrap()
+                .len()
+        };
+
+        assert!(people.is_dirty());
+        assert_eq!(on_disk(), 0);
+
+        people.flush().unwrap();
+
+        assert!(!people.is_dirty());
+        assert_eq!(on_disk(), 2);
+
+        people.insert("3", "Bob".to_string()).unwrap();
+
+        drop(people);
+        drop(table);
+        drop(db);
+
+        assert_eq!(on_disk(), 3);
+    }
+
+    #[test]
+    fn test_drop_deleted_table() {
+        let config = Configuration {
+            autosave: false,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<String>::new("test_drop_deleted_table", None, Some(config.clone()))
+                .unwrap();
+
+        db.reset_table("test_drop_deleted_table").unwrap();
+
+        let people = db.get_table().unwrap();
+        let file_path = people.read().unwrap().file_path.clone();
+
+        people
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        db.delete_table("test_drop_deleted_table").unwrap();
+        drop(people);
+        drop(db);
+
+        assert!(!file_path.exists());
+
+        let db = MooClient::<String>::new("test_drop_deleted_table", None, Some(config.clone()))
+            .unwrap();
+
+        db.table
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        let other = MooClient::<String>::new("test_drop_deleted_table", None, Some(config.clone()))
+            .unwrap();
+        let mut other = other.table.write().unwrap();
+
+        other.insert("2", "Jane".to_string()).unwrap();
+        other.flush().unwrap();
+
+        drop(db);
+
+        let people = MooClient::<String>::new("test_drop_deleted_table", None, Some(config))
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let mut people = people.write().unwrap();
+
+        assert!(people.get("1").is_err());
+        assert_eq!(people.get("2").unwrap(), "Jane");
+    }
+
+    #[test]
+    fn test_clear_memory() {
+        let config = Configuration {
+            autosave: false,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<u32>::new("test_clear_memory", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_clear_memory").unwrap();
+
+        let table = db.get_table().unwrap();
+        let mut scores = table.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.insert("2", 2).unwrap();
+        scores.flush().unwrap();
+
+        let on_disk = || {
+            MooClient::<u32>::new("test_clear_memory", None, Some(config.clone()))
+                .unwrap()
+                .get_table()
+                .unwrap()
+                .write()
+                .unwrap()
+                .records
+                .clone()
+        };
+
+        let events = scores.subscribe();
+
+        scores.clear_memory().unwrap();
+
+        assert!(scores.is_empty());
+        assert!(!scores.contains_key("1"));
+        assert!(!scores.is_dirty());
+        assert!(events.try_recv().is_err());
+        assert_eq!(on_disk().len(), 2);
+
+        scores.insert("2", 20).unwrap();
+        scores.insert("3", 3).unwrap();
+
+        assert_eq!(events.try_iter().count(), 2);
+
+        scores.flush().unwrap();
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![ChangeEvent::Deleted {
+                key: "1".to_string()
+            }]
+        );
+
+        let mut records = on_disk();
+
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value, 20);
+        assert_eq!(records[1].key, "3");
+
+        // Dropping a table right after clearing it leaves the file alone.
+        scores.clear_memory().unwrap();
+
+        drop(scores);
+        drop(table);
+        drop(db);
+
+        assert_eq!(on_disk().len(), 2);
+    }
+
+    #[test]
+    fn test_clear_memory_wal() {
+        let config = Configuration::builder().wal(true).build();
+
+        let mut db =
+            MooClient::<u32>::new("test_clear_memory_wal", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_clear_memory_wal").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.insert("2", 2).unwrap();
+        scores.clear_memory().unwrap();
+        scores.insert("3", 3).unwrap();
+
+        let reopened = MooClient::<u32>::new("test_clear_memory_wal", None, Some(config))
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.read().unwrap();
+
+        assert_eq!(reopened.len(), 1);
+        assert!(reopened.contains_key("3"));
+    }
+
+    #[test]
+    fn test_reload_from_disk() {
+        let mut db = MooClient::<u32>::new("test_reload_from_disk", None, None).unwrap();
+
+        db.reset_table("test_reload_from_disk").unwrap();
+
+        let reader = db.get_table().unwrap();
+        let mut reader = reader.write().unwrap();
+        let writer = MooClient::<u32>::new("test_reload_from_disk", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let mut writer = writer.write().unwrap();
+
+        writer.insert("1", 1).unwrap();
+        writer.insert("2", 2).unwrap();
+
+        assert!(reader.is_empty());
+
+        reader.reload_from_disk().unwrap();
+
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.get("2").unwrap(), 2);
+
+        reader.clear_memory().unwrap();
+        reader.reload_from_disk().unwrap();
+
+        assert!(!reader.is_dirty());
+        assert_eq!(reader.get("1").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        let config = Configuration {
+            pretty_json: true,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<u32>::new("main", Some(Path::new("db/test_pretty_json")), Some(config))
+                .unwrap();
+
+        db.reset_table("main").unwrap();
+
+        db.get_table()
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", 1)
+            .unwrap();
+
+        let file_path = Path::new("db/test_pretty_json/main.json");
+        let contents = fs::read_to_string(file_path).unwrap();
+
+        assert!(contents.contains("\n  {\n    \"key\": \"1\""));
+
+        let mut db =
+            MooClient::<u32>::new("main", Some(Path::new("db/test_pretty_json")), None).unwrap();
+
+        let table = db.get_table().unwrap();
+        let mut table = table.write().unwrap();
+
+        assert_eq!(table.get("1").unwrap(), 1);
+
+        table.insert("2", 2).unwrap();
+
+        // The checksum header is on its own line, followed by the minified records.
+        assert_eq!(fs::read_to_string(file_path).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_write_ahead_log() {
+        let config = Configuration {
+            wal: true,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<String>::new("test_write_ahead_log", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_write_ahead_log").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.insert("2", "Jane".to_string()).unwrap();
+        people.insert("3", "Jack".to_string()).unwrap();
+        people.update("1", "Johnny".to_string()).unwrap();
+        people.delete("2").unwrap();
+
+        let table_file = Path::new("db/moo/test_write_ahead_log.json");
+        let wal_file = Path::new("db/moo/test_write_ahead_log.wal");
+
+        // A crash in the middle of an append leaves a torn line at the end of the log.
+        let mut log = fs::read_to_string(wal_file).unwrap();
+        log.push_str("{\"Put\":{\"key\":\"4\"");
+        fs::write(wal_file, log).unwrap();
+
+        assert_eq!(fs::metadata(table_file).unwrap().len(), 0);
+
+        let open = || {
+            MooClient::<String>::new("test_write_ahead_log", None, Some(config.clone()))
+                .unwrap()
+                .get_table()
+                .unwrap()
+        };
+
+        let recovered = open();
+        let mut recovered = recovered.write().unwrap();
+
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(recovered.get("1").unwrap(), "Johnny");
+        assert!(!recovered.contains_key("2"));
+        assert!(!recovered.contains_key("4"));
+
+        recovered.insert("5", "Jill".to_string()).unwrap();
+
+        assert_eq!(open().write().unwrap().get("5").unwrap(), "Jill");
+
+        recovered.compact().unwrap();
+
+        assert!(!wal_file.exists());
+        assert!(fs::metadata(table_file).unwrap().len() > 0);
+        assert_eq!(open().write().unwrap().get("3").unwrap(), "Jack");
+    }
+
+    #[test]
+    fn test_auto_compact() {
+        let dir = Path::new("db/test_auto_compact");
+        let _ = fs::remove_dir_all(dir);
+
+        let config = Configuration::builder().auto_compact_ratio(1.0).build();
+
+        assert_eq!(
+            MooClient::<u32>::new("main", Some(dir), Some(config))
+                .unwrap_err()
+                .code,
+            MooErrorCodes::Warn
+        );
+
+        let config = Configuration::builder()
+            .wal(true)
+            .auto_compact_ratio(3.0)
+            .build();
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), Some(config)).unwrap();
+        let table = db.get_table().unwrap();
+        let mut table = table.write().unwrap();
+
+        for i in 0..50 {
+            table.insert(i.to_string(), i).unwrap();
+
+            let file_size = fs::metadata(dir.join("main.json")).unwrap().len();
+            let wal_size = fs::metadata(dir.join("main.wal")).map_or(0, |m| m.len());
+
+            assert!(wal_size <= file_size * 2);
+        }
+    }
+
+    #[test]
+    fn test_db_dir() {
+        let dir = Path::new("db/test_db_dir");
+        let _ = fs::remove_dir_all(dir);
+
+        let config = Configuration::builder().db_dir(dir).build();
+
+        let mut db = MooClient::<u32>::new("main", None, Some(config.clone())).unwrap();
+
+        db.get_table()
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", 1)
+            .unwrap();
+
+        assert!(dir.join("main.json").exists());
+        assert_eq!(db.path, dir);
+
+        let mut reopened = MooClient::<u32>::open_or_init("main", None, Some(config), |_| {
+            panic!("The table in db_dir should be found")
+        })
+        .unwrap();
+
+        assert_eq!(
+            reopened
+                .get_table()
+                .unwrap()
+                .write()
+                .unwrap()
+                .get("1")
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_lock_tables() {
+        let config = Configuration {
+            lock_tables: true,
+            ..Configuration::default()
+        };
+
+        let mut db =
+            MooClient::<String>::new("test_lock_tables", None, Some(config.clone())).unwrap();
+
+        let err = MooClient::<String>::new("test_lock_tables", None, Some(config.clone()))
+            .err()
+            .unwrap();
+
+        assert!(err.message.contains("already opened"));
+
+        // Handles of the same client share the lock.
+        let people = db.get_table().unwrap();
+        people
+            .write()
+            .unwrap()
+            .upsert("1", "John".to_string())
+            .unwrap();
+
+        drop(people);
+        drop(db);
+
+        let mut reopened =
+            MooClient::<String>::new("test_lock_tables", None, Some(config)).unwrap();
+
+        assert_eq!(
+            reopened
+                .get_table()
+                .unwrap()
+                .write()
+                .unwrap()
+                .get("1")
+                .unwrap(),
+            "John"
+        );
+    }
+
+    #[test]
+    fn test_encryption() {
+        let dir = Some(Path::new("db/test_encryption"));
+        let _ = fs::remove_dir_all("db/test_encryption");
+
+        let config = Configuration {
+            encryption_key: Some([7; 32]),
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<String>::new("people", dir, Some(config.clone())).unwrap();
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "123-45-6789".to_string()).unwrap();
+
+        let contents = fs::read("db/test_encryption/people.json").unwrap();
+
+        assert!(!String::from_utf8_lossy(&contents).contains("123-45-6789"));
+
+        let mut reopened = MooClient::<String>::new("people", dir, Some(config)).unwrap();
+
+        assert_eq!(
+            reopened
+                .get_table()
+                .unwrap()
+                .write()
+                .unwrap()
+                .get("1")
+                .unwrap(),
+            "123-45-6789"
+        );
+
+        let wrong_key = Configuration {
+            encryption_key: Some([8; 32]),
+            ..Configuration::default()
+        };
+
+        let err = MooClient::<String>::new("people", dir, Some(wrong_key))
+            .err()
+            .unwrap();
+
+        assert!(err.message.contains("Failed to decrypt table file"));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let dir = Some(Path::new("db/test_checksum"));
+        let file = "db/test_checksum/people.json";
+        let _ = fs::remove_dir_all("db/test_checksum");
+
+        let mut db = MooClient::<String>::new("people", dir, None).unwrap();
+
+        db.get_table()
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        let contents = fs::read_to_string(file).unwrap();
+
+        assert!(contents.starts_with("MOOC"));
+
+        fs::write(file, contents.replace("John", "Jahn")).unwrap();
+
+        let err = MooClient::<String>::new("people", dir, None).err().unwrap();
+
+        assert_eq!(err.code, MooErrorCodes::Corrupt);
+
+        // Files written before checksums were added still load.
+        fs::write(file, r#"[{"key":"1","value":"John"}]"#).unwrap();
+
+        let mut db = MooClient::<String>::new("people", dir, None).unwrap();
+
+        assert_eq!(
+            db.get_table().unwrap().write().unwrap().get("1").unwrap(),
+            "John"
+        );
+    }
+
+    #[test]
+    fn test_schema_migration() {
+        let dir = Some(Path::new("db/test_schema_migration"));
+
+        let _ = fs::remove_dir_all("db/test_schema_migration");
+
+        let mut db = MooClient::<String>::new("people", dir, None).unwrap();
+
+        db.get_table()
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+
+        let config = Configuration {
+            schema_version: 1,
+            migrate: Some(|version, value| {
+                assert_eq!(version, 0);
+                serde_json::json!([value, 30])
+            }),
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<(String, u32)>::new("people", dir, Some(config)).unwrap();
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        assert_eq!(people.get("1").unwrap(), ("John".to_string(), 30));
+
+        people.insert("2", ("Jane".to_string(), 25)).unwrap();
+
+        let contents = fs::read("db/test_schema_migration/people.json").unwrap();
+
+        assert_eq!(&contents[13..19], b"MOOS1\n");
+
+        let config = Configuration {
+            schema_version: 1,
+            migrate: Some(|_, _| panic!("Table file is already at the current schema version")),
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<(String, u32)>::new("people", dir, Some(config)).unwrap();
+
+        assert_eq!(db.get_table().unwrap().write().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_first_last() {
+        let mut db = MooClient::<String, u32>::new("test_first_last", None, None).unwrap();
+
+        db.reset_table("test_first_last").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        assert!(scores.first().is_none());
+        assert!(scores.last().is_none());
+
+        for (key, name) in [(30u32, "Bob"), (10, "John"), (20, "Jane")] {
+            scores.insert(key, name.to_string()).unwrap();
+        }
+
+        scores
+            .insert_with_ttl(5u32, "Expired".to_string(), Duration::ZERO)
+            .unwrap();
+
+        assert_eq!(scores.first().unwrap().key, 10);
+        assert_eq!(scores.last().unwrap().key, 30);
+
+        let keys: Vec<u32> = scores.iter_sorted().map(|record| record.key).collect();
+
+        assert_eq!(keys, vec![10, 20, 30]);
+        assert_eq!(scores.range(&10..&30).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut db = MooClient::<u32>::new("test_range", None, None).unwrap();
+
+        db.reset_table("test_range").unwrap();
+
+        let readings = db.get_table().unwrap();
+        let mut readings = readings.write().unwrap();
+
+        for (key, value) in [
+            ("2024-01-03T00:00:00Z", 3),
+            ("2024-01-01T00:00:00Z", 1),
+            ("2024-02-01T00:00:00Z", 4),
+            ("2024-01-02T00:00:00Z", 2),
+        ] {
+            readings.insert(key, value).unwrap();
+        }
+
+        let january = readings.range("2024-01-01".."2024-02-01").unwrap();
+        let values: Vec<u32> = january.iter().map(|record| record.value).collect();
+
+        assert_eq!(values, vec![1, 2, 3]);
+
+        let from_february = readings.range("2024-02-01"..).unwrap();
+
+        assert_eq!(from_february.len(), 1);
+        assert!(readings.range("2023".."2024").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let dir = Some(Path::new("db/test_snapshots"));
+
+        let _ = fs::remove_dir_all("db/test_snapshots");
+
+        let mut db = MooClient::<String>::new("people", dir, None).unwrap();
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+
+        let path = people.snapshot("before").unwrap();
+
+        assert!(path.ends_with("people.snapshot.before.json"));
+
+        people.update("1", "Jane".to_string()).unwrap();
+        people.insert("2", "Bob".to_string()).unwrap();
+        people.snapshot("after").unwrap();
+
+        assert_eq!(people.list_snapshots().unwrap(), vec!["after", "before"]);
+        assert_eq!(db.list_tables().unwrap(), vec!["people"]);
+
+        people.restore_snapshot("before").unwrap();
+
+        assert_eq!(people.len(), 1);
+        assert_eq!(people.get("1").unwrap(), "John");
+
+        let reopened = MooClient::<String>::new("people", dir, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.len(), 1);
+
+        people.delete_snapshot("after").unwrap();
+
+        assert_eq!(people.list_snapshots().unwrap(), vec!["before"]);
+        assert_eq!(
+            people.restore_snapshot("after").unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+        assert!(people.snapshot("../escape").is_err());
+    }
+
+    #[test]
+    fn test_json_string() {
+        let mut db = MooClient::<String>::new("test_json_string", None, None).unwrap();
+
+        db.reset_table("test_json_string").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.insert("2", "Jane".to_string()).unwrap();
+
+        let pretty = people.to_json_string(true).unwrap();
+        let compact = people.to_json_string(false).unwrap();
+
+        assert!(pretty.contains('\n'));
+        assert!(!compact.contains('\n'));
+
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let memory = MooClient::<String>::new("test_json_string", None, Some(config))
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let mut memory = memory.write().unwrap();
+
+        memory.insert("3", "Bob".to_string()).unwrap();
+        memory.from_json_string(&pretty, ImportMode::Merge).unwrap();
+
+        assert_eq!(memory.len(), 3);
+
+        memory
+            .from_json_string(&compact, ImportMode::Replace)
+            .unwrap();
+
+        assert_eq!(memory.to_json_string(false).unwrap(), compact);
+        assert!(memory
+            .from_json_string("not json", ImportMode::Merge)
+            .is_err());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut db = MooClient::<u32>::new("test_retain", None, None).unwrap();
+
+        db.reset_table("test_retain").unwrap();
+
+        let ages = db.get_table().unwrap();
+        let mut ages = ages.write().unwrap();
+
+        for (key, age) in [("John", 17), ("Jane", 30), ("Bob", 12), ("Alice", 45)] {
+            ages.insert(key, age).unwrap();
+        }
+
+        let events = ages.subscribe();
+
+        assert_eq!(ages.retain(|record| record.value >= 18).unwrap(), 2);
+        assert_eq!(ages.retain(|record| record.value >= 18).unwrap(), 0);
+
+        assert_eq!(ages.len(), 2);
+        assert!(!ages.contains_key("John"));
+        assert_eq!(ages.get("Alice").unwrap(), 45);
+        assert_eq!(events.try_iter().count(), 2);
+
+        let reopened = MooClient::<u32>::new("test_retain", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.len(), 2);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let mut db = MooClient::<u32>::new("test_dry_run", None, None).unwrap();
+
+        db.reset_table("test_dry_run").unwrap();
+
+        let ages = db.get_table().unwrap();
+        let mut ages = ages.write().unwrap();
+
+        for (key, age) in [("John", 17), ("Jane", 30), ("Bob", 12)] {
+            ages.insert(key, age).unwrap();
+        }
+
+        let events = ages.subscribe();
+
+        assert_eq!(ages.count_matching(|record| record.value < 18), 2);
+        assert_eq!(
+            ages.retain_with(|record| record.value >= 18, RunMode::DryRun)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            ages.delete_many_with(vec!["John", "Jane", "Jack"], RunMode::DryRun)
+                .unwrap(),
+            2
+        );
+
+        assert_eq!(ages.len(), 3);
+        assert!(!ages.is_dirty());
+        assert_eq!(events.try_iter().count(), 0);
+
+        assert_eq!(
+            ages.delete_many_with(vec!["John", "Jack"], RunMode::Apply)
+                .unwrap(),
+            1
+        );
+        assert_eq!(ages.len(), 2);
+    }
+
+    #[test]
+    fn test_modify() {
+        let mut db = MooClient::<Vec<u32>>::new("test_modify", None, None).unwrap();
+
+        db.reset_table("test_modify").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("John", vec![1, 2]).unwrap();
+
+        scores.modify("John", |scores| scores.push(3)).unwrap();
+
+        assert_eq!(scores.get("John").unwrap(), vec![1, 2, 3]);
+        assert_eq!(
+            scores
+                .modify("Jane", |scores| scores.clear())
+                .unwrap_err()
+                .code,
+            MooErrorCodes::NotFound
+        );
+
+        let reopened = MooClient::<Vec<u32>>::new("test_modify", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.all()[0].value, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_try_insert() {
+        let mut db = MooClient::<String>::new("test_try_insert", None, None).unwrap();
+
+        db.reset_table("test_try_insert").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        assert!(people.try_insert("1", "John".to_string()).unwrap());
+        assert!(!people.try_insert("1", "Jane".to_string()).unwrap());
+
+        assert_eq!(people.get("1").unwrap(), "John");
+        assert_eq!(people.len(), 1);
+    }
+
+    #[test]
+    fn test_for_each() {
+        let mut db = MooClient::<u32>::new("test_for_each", None, None).unwrap();
+
+        db.reset_table("test_for_each").unwrap();
+
+        let ages = db.get_table().unwrap();
+        let mut ages = ages.write().unwrap();
+
+        for (key, age) in [("John", 17), ("Jane", 30), ("Bob", 12)] {
+            ages.insert(key, age).unwrap();
+        }
+
+        let mut total = 0;
+        let mut keys = Vec::new();
+
+        ages.for_each(|record| {
+            total += record.value;
+            keys.push(record.key.clone());
+        });
+
+        assert_eq!(total, 59);
+        assert_eq!(keys, vec!["John", "Jane", "Bob"]);
+    }
+
+    #[test]
+    fn test_compact_sorted() {
+        let mut db = MooClient::<String>::new("test_compact_sorted", None, None).unwrap();
+
+        db.reset_table("test_compact_sorted").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        for (key, name) in [("c", "Bob"), ("a", "John"), ("b", "Jane")] {
+            people.insert(key, name.to_string()).unwrap();
+        }
+
+        people.compact_sorted().unwrap();
+
+        assert_eq!(people.keys().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(people.get("c").unwrap(), "Bob");
+
+        let reopened = MooClient::<String>::new("test_compact_sorted", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.keys().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut db = MooClient::<Vec<u32>>::new("test_entry", None, None).unwrap();
 
-        people.update_many(u).unwrap();
+        db.reset_table("test_entry").unwrap();
 
-        assert_eq!(people.get("4").unwrap(), "Example Person 4 updated");
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("John", vec![1]).unwrap();
+
+        let events = scores.subscribe();
+
+        {
+            let mut john = scores.entry("John").unwrap();
+
+            john.push(2);
+            john.push(3);
+        }
+
+        assert_eq!(scores.get("John").unwrap(), vec![1, 2, 3]);
+        assert!(!scores.is_dirty());
+
+        assert_eq!(scores.entry("John").unwrap().len(), 3);
+
+        let mut john = scores.entry("John").unwrap();
+        john.clear();
+        john.commit().unwrap();
+
+        assert_eq!(events.try_iter().count(), 2);
+        assert_eq!(
+            scores.entry("Jane").unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+
+        let reopened = MooClient::<Vec<u32>>::new("test_entry", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert!(reopened.all()[0].value.is_empty());
+    }
+
+    #[test]
+    fn test_all_tables() {
+        let dir = Path::new("db/test_all_tables");
+        let _ = fs::remove_dir_all(dir);
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), None).unwrap();
+
+        db.get_table()
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", 1)
+            .unwrap();
+        db.create_table("other")
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", 1)
+            .unwrap();
+
+        fs::write(dir.join("notes.txt"), "keep me").unwrap();
+
+        db.reset_all_tables().unwrap();
+
+        assert!(db.get_table().unwrap().write().unwrap().is_empty());
+        assert!(db.open_table("other").unwrap().write().unwrap().is_empty());
+        assert_eq!(db.list_tables().unwrap(), vec!["main", "other"]);
+
+        db.delete_all_tables().unwrap();
+
+        assert!(db.list_tables().unwrap().is_empty());
+        assert!(dir.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_size_on_disk() {
+        let dir = Path::new("db/test_size_on_disk");
+        let _ = fs::remove_dir_all(dir);
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), None).unwrap();
+
+        let main = db.get_table().unwrap();
+        let mut main = main.write().unwrap();
+        main.insert("1", 1).unwrap();
+
+        let other = db.create_table("other").unwrap();
+        let mut other = other.write().unwrap();
+        other.insert("1", 1).unwrap();
+        other.insert("2", 2).unwrap();
+
+        let main_size = main.size_on_disk().unwrap();
+        let other_size = other.size_on_disk().unwrap();
+
+        assert_eq!(
+            main_size,
+            fs::metadata(dir.join("main.json")).unwrap().len()
+        );
+        assert!(other_size > main_size);
+        assert_eq!(db.total_size().unwrap(), main_size + other_size);
+
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Default::default()
+        };
+
+        let mut memory = MooClient::<u32>::new("test_size_memory", None, Some(config)).unwrap();
+
+        let table = memory.get_table().unwrap();
+        let mut table = table.write().unwrap();
+        let empty_size = table.size_on_disk().unwrap();
+
+        table.insert("1", 1).unwrap();
+        assert!(table.size_on_disk().unwrap() > empty_size);
+    }
+
+    #[test]
+    fn test_readonly() {
+        let mut db = MooClient::<u32>::new("test_readonly", None, None).unwrap();
+
+        db.reset_table("test_readonly").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.insert("2", 2).unwrap();
+        scores
+            .insert_with_ttl("3", 3, Duration::from_millis(0))
+            .unwrap();
+
+        let total = |table: ReadOnlyTable<'_, String, u32>| {
+            table
+                .get_all()
+                .unwrap()
+                .iter()
+                .map(|r| r.value)
+                .sum::<u32>()
+        };
+
+        let reader = scores.as_readonly();
+
+        assert_eq!(total(reader), 3);
+        assert_eq!(reader.get("1").unwrap(), 1);
+        assert_eq!(reader.get("3").unwrap_err().code, MooErrorCodes::NotFound);
+        assert_eq!(reader.get_many(&["2", "3", "2"]).unwrap().len(), 1);
+        assert_eq!(reader.find(|value| *value > 1).unwrap().len(), 1);
+        assert!(reader.contains_key("2"));
+        assert!(!reader.contains_key("3"));
+        assert_eq!(reader.len(), 2);
+        assert!(!reader.is_empty());
+        assert_eq!(scores.all().len(), 3);
+    }
+
+    #[test]
+    fn test_scan_pattern() {
+        let mut db = MooClient::<u32>::new("test_scan_pattern", None, None).unwrap();
+
+        db.reset_table("test_scan_pattern").unwrap();
+
+        let keys = db.get_table().unwrap();
+        let mut keys = keys.write().unwrap();
+
+        for key in [
+            "user:1:settings",
+            "user:1:profile",
+            "user:22:settings",
+            "user::settings",
+            "admin:1:settings",
+        ] {
+            keys.insert(key, 0).unwrap();
+        }
+
+        let matching = |pattern: &str| {
+            keys.scan_pattern(pattern)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.key)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            matching("user:*:settings"),
+            vec!["user:1:settings", "user:22:settings", "user::settings"]
+        );
+        assert_eq!(
+            matching("user:?:*"),
+            vec!["user:1:settings", "user:1:profile"]
+        );
+        assert_eq!(
+            matching("*:1:*s"),
+            vec!["user:1:settings", "admin:1:settings"]
+        );
+        assert_eq!(matching("*").len(), 5);
+        assert_eq!(matching("admin:1:settings"), vec!["admin:1:settings"]);
+        assert!(matching("user:?").is_empty());
+        assert!(matching("").is_empty());
+    }
+
+    #[test]
+    fn test_file_naming() {
+        let dir = Path::new("db/test_file_naming");
+        let _ = fs::remove_dir_all(dir);
+
+        let config = Configuration::builder()
+            .file_prefix("moo_")
+            .file_extension("db")
+            .wal(true)
+            .build();
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), Some(config.clone())).unwrap();
+
+        let main = db.get_table().unwrap();
+        let mut main = main.write().unwrap();
+        main.insert("1", 1).unwrap();
+        main.snapshot("backup").unwrap();
+
+        db.create_table("other").unwrap();
+
+        fs::write(dir.join("main.json"), "[]").unwrap();
+        fs::write(dir.join("moo_notes.txt"), "keep me").unwrap();
+
+        assert!(dir.join("moo_main.db").exists());
+        assert!(dir.join("moo_main.wal").exists());
+        assert!(dir.join("moo_main.snapshot.backup.db").exists());
+        assert_eq!(db.list_tables().unwrap(), vec!["main", "other"]);
+        assert_eq!(main.list_snapshots().unwrap(), vec!["backup"]);
+
+        let mut reopened = MooClient::<u32>::new("main", Some(dir), Some(config)).unwrap();
+
+        assert_eq!(
+            reopened
+                .get_table()
+                .unwrap()
+                .write()
+                .unwrap()
+                .get("1")
+                .unwrap(),
+            1
+        );
+
+        reopened.delete_all_tables().unwrap();
+
+        assert!(!dir.join("moo_main.db").exists());
+        assert!(dir.join("main.json").exists());
+        assert!(dir.join("moo_notes.txt").exists());
+    }
+
+    #[test]
+    fn test_metrics() {
+        let dir = Path::new("db/test_metrics");
+        let _ = fs::remove_dir_all(dir);
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), None).unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.upsert("2", 2).unwrap();
+        scores.update("1", 10).unwrap();
+        scores.delete("2").unwrap();
+
+        assert_eq!(scores.get("1").unwrap(), 10);
+        assert!(scores.get("2").is_err());
+        assert!(scores.insert("1", 1).is_err());
+
+        drop(scores);
+
+        let metrics = db.get_table().unwrap().read().unwrap().metrics();
+
+        assert_eq!(metrics.gets, 2);
+        assert_eq!(metrics.inserts, 2);
+        assert_eq!(metrics.updates, 1);
+        assert_eq!(metrics.deletes, 1);
+        assert_eq!(metrics.saves, 4);
+        assert!(metrics.save_duration > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_validator() {
+        let mut db = MooClient::<i64>::new("test_validator", None, None).unwrap();
+
+        db.reset_table("test_validator").unwrap();
+
+        let balances = db.get_table().unwrap();
+        let mut balances = balances.write().unwrap();
+
+        balances.set_validator(|key, value| {
+            if key.is_empty() {
+                return Err("Username can't be empty.".to_string());
+            }
+
+            if *value < 0 {
+                return Err(format!("Balance of {} can't be negative.", key));
+            }
+
+            Ok(())
+        });
+
+        balances.insert("John", 10).unwrap();
+
+        let err = balances.insert("Jane", -5).unwrap_err();
+
+        assert_eq!(err.code, MooErrorCodes::Warn);
+        assert_eq!(err.message, "Balance of Jane can't be negative.");
+        assert!(balances.insert("", 5).is_err());
+        assert!(balances.update("John", -1).is_err());
+        assert!(balances.upsert("Bob", -1).is_err());
+        assert!(balances
+            .upsert_many(vec![
+                MooRecord {
+                    key: "Bob".to_string(),
+                    value: 1,
+                    expires_at: None,
+                    deleted: false,
+                },
+                MooRecord {
+                    key: "Jack".to_string(),
+                    value: -1,
+                    expires_at: None,
+                    deleted: false,
+                },
+            ])
+            .is_err());
+        assert!(balances.batch().update("John", -1).commit().is_err());
+
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances.get("John").unwrap(), 10);
+        assert!(!balances.is_dirty());
+
+        balances.clear_validator();
+        balances.update("John", -1).unwrap();
+
+        let reopened = MooClient::<i64>::new("test_validator", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.all()[0].value, -1);
+    }
+
+    #[test]
+    fn test_get_or() {
+        let mut db = MooClient::<u32>::new("test_get_or", None, None).unwrap();
+
+        db.reset_table("test_get_or").unwrap();
+
+        let counts = db.get_table().unwrap();
+        let mut counts = counts.write().unwrap();
+
+        counts.insert("visits", 3).unwrap();
+        counts
+            .insert_with_ttl("expired", 9, Duration::from_millis(0))
+            .unwrap();
+
+        assert_eq!(counts.get_or("visits", 10), 3);
+        assert_eq!(counts.get_or("likes", 10), 10);
+        assert_eq!(counts.get_or("expired", 10), 10);
+        assert_eq!(counts.get_or_default("visits"), 3);
+        assert_eq!(counts.get_or_default("likes"), 0);
+        assert!(!counts.contains_key("likes"));
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let mut db = MooClient::<u32>::new("test_replace_all", None, None).unwrap();
+
+        db.reset_table("test_replace_all").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.insert("2", 2).unwrap();
+
+        let events = scores.subscribe();
+
+        let record = |key: &str, value: u32| MooRecord {
+            key: key.to_string(),
+            value,
+            expires_at: None,
+            deleted: false,
+        };
+
+        let err = scores
+            .replace_all(vec![record("3", 3), record("3", 4)])
+            .unwrap_err();
+
+        assert_eq!(err.code, MooErrorCodes::Warn);
+        assert_eq!(scores.len(), 2);
+
+        scores
+            .replace_all(vec![record("2", 20), record("3", 3)])
+            .unwrap();
+
+        assert_eq!(scores.get("2").unwrap(), 20);
+        assert!(!scores.contains_key("1"));
+        assert!(matches!(
+            events.try_iter().collect::<Vec<_>>().as_slice(),
+            [
+                ChangeEvent::Deleted { .. },
+                ChangeEvent::Updated { .. },
+                ChangeEvent::Inserted { .. },
+            ]
+        ));
+
+        let reopened = MooClient::<u32>::new("test_replace_all", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.all(), scores.all());
+    }
+
+    #[test]
+    fn test_insert_many_duplicates() {
+        let mut db = MooClient::<u32>::new("test_insert_many_duplicates", None, None).unwrap();
+
+        db.reset_table("test_insert_many_duplicates").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("0", 0).unwrap();
+
+        let record = |key: &str, value: u32| MooRecord {
+            key: key.to_string(),
+            value,
+            expires_at: None,
+            deleted: false,
+        };
+
+        let err = scores
+            .insert_many(vec![record("1", 1), record("2", 2), record("1", 3)])
+            .unwrap_err();
+
+        assert_eq!(err.code, MooErrorCodes::Warn);
+        assert!(err.message.contains("\"1\""));
+        assert_eq!(scores.len(), 1);
+        assert!(!scores.contains_key("1"));
+        assert!(!scores.is_dirty());
+    }
+
+    #[test]
+    fn test_soft_delete() {
+        let config = Configuration::builder().soft_delete(true).build();
+
+        let mut db = MooClient::<u32>::new("test_soft_delete", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_soft_delete").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        scores.insert("1", 1).unwrap();
+        scores.insert("2", 2).unwrap();
+        scores.insert("3", 3).unwrap();
+
+        scores.delete("1").unwrap();
+        scores.delete_many(vec!["2"]).unwrap();
+
+        assert_eq!(scores.records.len(), 3);
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores.all().len(), 1);
+        assert_eq!(scores.iter().count(), 1);
+        assert_eq!((&*scores).into_iter().count(), 1);
+        assert_eq!(scores.keys().collect::<Vec<_>>(), vec!["3"]);
+        assert_eq!(scores.values().sum::<u32>(), 3);
+        assert_eq!(scores.get_page(0, 10).unwrap().len(), 1);
+        assert_eq!(scores.page_count(1), 1);
+        assert_eq!(scores.count_by(|value| value % 2).len(), 1);
+        assert!(!scores.contains_key("1"));
+        assert_eq!(scores.get("1").unwrap_err().code, MooErrorCodes::NotFound);
+        assert_eq!(scores.get_all().unwrap().len(), 1);
+        assert!(scores.update("2", 20).is_err());
+        assert!(scores.delete("2").is_err());
+
+        let reopen = || {
+            MooClient::<u32>::new("test_soft_delete", None, Some(config.clone()))
+                .unwrap()
+                .get_table()
+                .unwrap()
+        };
+
+        assert!(reopen().write().unwrap().records[0].deleted);
+
+        scores.restore("1").unwrap();
+
+        assert_eq!(scores.get("1").unwrap(), 1);
+        assert_eq!(
+            scores.restore("1").unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+
+        scores.insert("2", 22).unwrap();
+
+        assert_eq!(scores.get("2").unwrap(), 22);
+
+        scores.delete("3").unwrap();
+
+        assert_eq!(scores.purge_deleted().unwrap(), 1);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(reopen().write().unwrap().len(), 2);
+        assert_eq!(
+            scores.restore("3").unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+    }
+
+    #[test]
+    fn test_soft_delete_paths() {
+        let config = Configuration::builder().soft_delete(true).build();
+
+        let mut db =
+            MooClient::<u32>::new("test_soft_delete_paths", None, Some(config.clone())).unwrap();
+
+        db.reset_table("test_soft_delete_paths").unwrap();
+
+        let scores = db.get_table().unwrap();
+        let mut scores = scores.write().unwrap();
+
+        let record = |key: &str, value| MooRecord {
+            key: key.to_string(),
+            value,
+            expires_at: None,
+            deleted: false,
+        };
+
+        scores
+            .insert_many(vec![record("a", 1), record("b", 2), record("c", 3)])
+            .unwrap();
+        scores.delete("a").unwrap();
+
+        // Inserting over a deleted record replaces it instead of adding a second record with the key.
+        scores.insert_many(vec![record("a", 10)]).unwrap();
+
+        assert_eq!(scores.records.len(), 3);
+        assert_eq!(scores.get("a").unwrap(), 10);
+
+        assert_eq!(scores.retain(|record| record.value != 2).unwrap(), 1);
+        assert!(scores.records.iter().any(|record| record.deleted));
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores.retain(|record| record.value != 2).unwrap(), 0);
+        assert_eq!(scores.count_matching(|_| true), 2);
+
+        scores.restore("b").unwrap();
+        scores.batch().delete("b").commit().unwrap();
+
+        assert_eq!(scores.records.len(), 3);
+        assert!(scores.batch().delete("b").commit().is_err());
+        assert!(scores.restore("b").is_ok());
+
+        scores.delete("c").unwrap();
+
+        assert_eq!(
+            scores.rename_key("c", "d").unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+
+        // Renaming onto a deleted record replaces it.
+        scores.rename_key("b", "c").unwrap();
+
+        assert_eq!(scores.get("c").unwrap(), 2);
+        assert_eq!(scores.records.len(), 2);
+        assert!(!scores.contains_key("b"));
+
+        let reopened = MooClient::<u32>::new("test_soft_delete_paths", None, Some(config))
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.records.len(), 2);
+    }
+
+    #[test]
+    fn test_u64_keys() {
+        let mut db = MooClient::<String>::new("test_u64_keys", None, None).unwrap();
+
+        db.reset_table("test_u64_keys").unwrap();
+
+        let users = db.get_table().unwrap();
+        let mut users = users.write().unwrap();
+
+        users.insert_u64(42, "John".to_string()).unwrap();
+        users.insert_u64(7, "Jane".to_string()).unwrap();
+        users.insert("007", "Bond".to_string()).unwrap();
+        users.insert("admin", "Bob".to_string()).unwrap();
+
+        assert!(users.insert_u64(42, "Jack".to_string()).is_err());
+        assert_eq!(users.get_by_u64(42).unwrap(), "John");
+        assert_eq!(users.get_by_u64(7).unwrap(), "Jane");
+        assert_eq!(
+            users.get_by_u64(1).unwrap_err().code,
+            MooErrorCodes::NotFound
+        );
+        assert_eq!(users.keys_as_u64(), vec![42, 7]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut db = MooClient::<Vec<String>>::new("test_merge", None, None).unwrap();
+
+        db.reset_table("test_merge").unwrap();
+
+        let tags = db.get_table().unwrap();
+        let mut tags = tags.write().unwrap();
+
+        let append = |current: Option<&Vec<String>>, mut operand: Vec<String>| {
+            let mut merged = current.cloned().unwrap_or_default();
+            merged.append(&mut operand);
+            merged
+        };
+
+        let merged = tags
+            .merge("post", vec!["rust".to_string()], append)
+            .unwrap();
+
+        assert_eq!(merged, vec!["rust"]);
+
+        tags.merge("post", vec!["db".to_string()], append).unwrap();
+
+        assert_eq!(tags.get("post").unwrap(), vec!["rust", "db"]);
+
+        let reopened = MooClient::<Vec<String>>::new("test_merge", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.all()[0].value, vec!["rust", "db"]);
+    }
+
+    #[test]
+    fn test_recover_corrupt() {
+        let dir = Path::new("db/test_recover_corrupt");
+        let file_path = dir.join("main.json");
+
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            &file_path,
+            r#"[{"key":"1","value":1},{"key":"2","value":2},{"key":"3","va"#,
+        )
+        .unwrap();
+
+        assert!(MooClient::<u32>::new("main", Some(dir), None).is_err());
+
+        let config = Configuration {
+            recover_corrupt: true,
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), Some(config.clone())).unwrap();
+        let table = db.get_table().unwrap();
+        let mut table = table.write().unwrap();
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get("2").unwrap(), 2);
+
+        table.insert("3", 3).unwrap();
+
+        let mut contents = fs::read(&file_path).unwrap();
+
+        contents.extend_from_slice(b"garbage");
+        fs::write(&file_path, contents).unwrap();
+
+        assert!(MooClient::<u32>::new("main", Some(dir), None).is_err());
+
+        let mut db = MooClient::<u32>::new("main", Some(dir), Some(config)).unwrap();
+
+        assert_eq!(db.get_table().unwrap().write().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_get_table_sees_changes() {
+        let mut db = MooClient::<u32>::new("test_get_table_sees_changes", None, None).unwrap();
+
+        db.reset_table("test_get_table_sees_changes").unwrap();
+
+        let first = db.get_table().unwrap();
+        let second = db.get_table().unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+
+        first.write().unwrap().insert("1", 1).unwrap();
+
+        assert_eq!(second.write().unwrap().get("1").unwrap(), 1);
+
+        second.write().unwrap().update("1", 2).unwrap();
+
+        assert_eq!(first.write().unwrap().get("1").unwrap(), 2);
+        assert_eq!(db.table.write().unwrap().get("1").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_get_table_shared() {
+        let config = Configuration {
+            storage: StorageTypes::Memory,
+            ..Configuration::default()
+        };
+
+        let mut db = MooClient::<u32>::new("test_get_table_shared", None, Some(config)).unwrap();
+
+        let first = db.get_table().unwrap();
+        let second = db.get_table().unwrap();
+
+        first.write().unwrap().insert("1", 1).unwrap();
+
+        assert_eq!(second.read().unwrap().as_readonly().get("1").unwrap(), 1);
+
+        let writer = thread::spawn(move || second.write().unwrap().insert("2", 2).unwrap());
+
+        writer.join().unwrap();
+
+        assert_eq!(first.read().unwrap().len(), 2);
+        assert_eq!(db.stats_all().unwrap()["test_get_table_shared"].records, 2);
+        assert!(db.total_size().unwrap() > 0);
+
+        let other = db.create_table("other").unwrap();
+
+        other.write().unwrap().insert("1", 10).unwrap();
+
+        assert_eq!(
+            db.open_table("other")
+                .unwrap()
+                .write()
+                .unwrap()
+                .get("1")
+                .unwrap(),
+            10
+        );
+        assert_eq!(db.stats_all().unwrap()["other"].records, 1);
+
+        db.reset_table("test_get_table_shared").unwrap();
+
+        assert!(first.read().unwrap().is_empty());
+
+        db.delete_table("other").unwrap();
+
+        assert!(other.read().unwrap().is_empty());
+        assert!(db.open_table("other").is_err());
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let mut db = MooClient::<String>::new("test_subscribe", None, None).unwrap();
+
+        db.reset_table("test_subscribe").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        let first = people.subscribe();
+        let second = people.subscribe();
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.update("1", "Jane".to_string()).unwrap();
+        people.delete("1").unwrap();
+
+        for receiver in [first, second] {
+            let events: Vec<ChangeEvent<String>> = receiver.try_iter().collect();
+
+            assert_eq!(
+                events,
+                vec![
+                    ChangeEvent::Inserted {
+                        key: "1".to_string(),
+                        value: "John".to_string(),
+                    },
+                    ChangeEvent::Updated {
+                        key: "1".to_string(),
+                        value: "Jane".to_string(),
+                    },
+                    ChangeEvent::Deleted {
+                        key: "1".to_string(),
+                    },
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_subscribe_dropped_receiver() {
+        let mut db =
+            MooClient::<String>::new("test_subscribe_dropped_receiver", None, None).unwrap();
+
+        db.reset_table("test_subscribe_dropped_receiver").unwrap();
+
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
+
+        let dropped = people.subscribe();
+        let kept = people.subscribe();
+
+        drop(dropped);
+
+        people.insert("1", "John".to_string()).unwrap();
+        people.delete("1").unwrap();
+
+        assert_eq!(kept.try_iter().count(), 2);
+        assert_eq!(people.subscribers.lock().unwrap().len(), 1);
     }
 }
@@ -0,0 +1,339 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::Path;
+
+use crate::core::{MooClient, MooTable};
+use crate::{Configuration, MooKey, MooRecords, MooResult};
+
+/// A database client for a schemaless table, where every record holds any JSON value.
+///
+/// Different keys can hold differently shaped values, which is useful for stores like configs
+/// where the values are heterogeneous. Create one with `MooClient::new_dynamic`.
+pub type DynamicMooClient = MooClient<Value>;
+
+/// A schemaless table whose records hold `serde_json::Value`s. See `DynamicMooClient`.
+pub type DynamicMooTable = MooTable<String, Value>;
+
+impl MooClient<Value> {
+    /// Creates a new Moo database instance with a schemaless table.
+    ///
+    /// Takes the same `name`, `dir` and `config` as `new`.
+    ///
+    /// Bincode isn't self-describing, so the table has to use the JSON or MessagePack format.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn new_dynamic(
+        name: &str,
+        dir: Option<&Path>,
+        config: Option<Configuration>,
+    ) -> MooResult<DynamicMooClient> {
+        Self::new(name, dir, config)
+    }
+}
+
+impl<K> MooTable<K, Value>
+where
+    K: MooKey,
+{
+    /// Insert a new record into the table with any serializable value.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert. It is converted to JSON, so any type that implements `Serialize` can be stored.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn insert_value<V>(&mut self, key: impl Into<K>, value: &V) -> MooResult<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.insert(key, serde_json::to_value(value)?)
+    }
+
+    /// Insert a record into the table with any serializable value, or update it if the key already exists.
+    ///
+    /// The `key` of the record to upsert.
+    ///
+    /// The `value` of the record to upsert.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn upsert_value<V>(&mut self, key: impl Into<K>, value: &V) -> MooResult<()>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.upsert(key, serde_json::to_value(value)?)
+    }
+
+    /// Update a record in the table with any serializable value.
+    ///
+    /// The `key` of the record to update.
+    ///
+    /// The `value` to update the record with. It doesn't need to have the same shape as the old value.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn update_value<Q, V>(&mut self, key: &Q, value: &V) -> MooResult<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+        V: Serialize + ?Sized,
+    {
+        self.update(key, serde_json::to_value(value)?)
+    }
+
+    /// Get a record from the table as a concrete type.
+    ///
+    /// The `key` of the record to get.
+    ///
+    /// The value is deserialized into `V`, use `get` instead to get the raw JSON value.
+    ///
+    /// Returns a `MooResult` with the value, or an error if it doesn't have the shape of `V`.
+    pub fn get_value<Q, V>(&mut self, key: &Q) -> MooResult<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+        V: DeserializeOwned,
+    {
+        Ok(serde_json::from_value(self.get(key)?)?)
+    }
+
+    /// Start a query filtering the records by the fields of their values.
+    ///
+    /// Returns a `Query` to chain the filters on, which are run together with `run`.
+    pub fn query(&self) -> Query<'_, K> {
+        Query {
+            table: self,
+            filters: Vec::new(),
+        }
+    }
+}
+
+/// How a field is compared to the value in a query filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+/// A filter over the records of a schemaless table, like `table.query().where_eq("status", json!("active")).run()`.
+///
+/// Every filter compares the field at a path in the record value to a JSON value, and a record has to match all of them.
+/// Paths are field names separated by dots like `address.city`, or JSON pointers like `/tags/0`.
+/// Numbers are compared by value and strings lexicographically, ordering other types never matches.
+/// A record without the field only matches `where_ne`.
+#[derive(Debug)]
+pub struct Query<'a, K>
+where
+    K: MooKey,
+{
+    /// The table the query runs on.
+    table: &'a MooTable<K, Value>,
+    /// The field path, comparison and value of every filter.
+    filters: Vec<(String, Comparison, Value)>,
+}
+
+impl<K> Query<'_, K>
+where
+    K: MooKey,
+{
+    /// Adds a filter to the query.
+    ///
+    /// This is an internal function and can't be used directly by the user.
+    fn filter(mut self, path: &str, comparison: Comparison, value: Value) -> Self {
+        let pointer = if path.is_empty() || path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{}", path.replace('.', "/"))
+        };
+
+        self.filters.push((pointer, comparison, value));
+        self
+    }
+
+    /// Only match records whose field at `path` equals `value`.
+    pub fn where_eq(self, path: &str, value: Value) -> Self {
+        self.filter(path, Comparison::Eq, value)
+    }
+
+    /// Only match records whose field at `path` doesn't equal `value`, or that don't have the field.
+    pub fn where_ne(self, path: &str, value: Value) -> Self {
+        self.filter(path, Comparison::Ne, value)
+    }
+
+    /// Only match records whose field at `path` is greater than `value`.
+    pub fn where_gt(self, path: &str, value: Value) -> Self {
+        self.filter(path, Comparison::Gt, value)
+    }
+
+    /// Only match records whose field at `path` is greater than or equal to `value`.
+    pub fn where_gte(self, path: &str, value: Value) -> Self {
+        self.filter(path, Comparison::Gte, value)
+    }
+
+    /// Only match records whose field at `path` is less than `value`.
+    pub fn where_lt(self, path: &str, value: Value) -> Self {
+        self.filter(path, Comparison::Lt, value)
+    }
+
+    /// Only match records whose field at `path` is less than or equal to `value`.
+    pub fn where_lte(self, path: &str, value: Value) -> Self {
+        self.filter(path, Comparison::Lte, value)
+    }
+
+    /// Run the query on the table. Expired records are skipped.
+    ///
+    /// Returns a `MooResult` with the matching records in table order, an empty vector if nothing matches.
+    pub fn run(&self) -> MooResult<MooRecords<Value, K>> {
+        self.table.find(|value| {
+            self.filters.iter().all(|(pointer, comparison, expected)| {
+                matches(value.pointer(pointer), *comparison, expected)
+            })
+        })
+    }
+}
+
+/// Whether a field matches a query filter.
+fn matches(field: Option<&Value>, comparison: Comparison, expected: &Value) -> bool {
+    let field = match field {
+        Some(field) => field,
+        None => return comparison == Comparison::Ne,
+    };
+
+    let order = compare(field, expected);
+    let equal = order == Some(Ordering::Equal) || field == expected;
+
+    match comparison {
+        Comparison::Eq => equal,
+        Comparison::Ne => !equal,
+        Comparison::Gt => order == Some(Ordering::Greater),
+        Comparison::Gte => matches!(order, Some(Ordering::Greater | Ordering::Equal)),
+        Comparison::Lt => order == Some(Ordering::Less),
+        Comparison::Lte => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+    }
+}
+
+/// Orders two JSON values of the same type, numbers by value and strings lexicographically.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::core::MooClient;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[test]
+    fn test_dynamic_table() {
+        let mut db = MooClient::new_dynamic("test_dynamic_table", None, None).unwrap();
+
+        db.reset_table("test_dynamic_table").unwrap();
+
+        let settings = db.get_table().unwrap();
+        let mut settings = settings.write().unwrap();
+
+        let server = Server {
+            host: "localhost".to_string(),
+            port: 8080,
+        };
+
+        settings.insert_value("server", &server).unwrap();
+        settings.insert_value("retries", &3).unwrap();
+        settings.insert("tags", json!(["a", "b"])).unwrap();
+
+        assert_eq!(settings.get_value::<_, Server>("server").unwrap(), server);
+        assert_eq!(settings.get_value::<_, u32>("retries").unwrap(), 3);
+        assert_eq!(settings.get("tags").unwrap(), json!(["a", "b"]));
+        assert!(settings.get_value::<_, u32>("server").is_err());
+
+        settings.update_value("retries", "forever").unwrap();
+        settings.upsert_value("debug", &true).unwrap();
+
+        let reopened = MooClient::new_dynamic("test_dynamic_table", None, None)
+            .unwrap()
+            .get_table()
+            .unwrap();
+        let reopened = reopened.write().unwrap();
+
+        assert_eq!(reopened.len(), 4);
+        assert_eq!(reopened.all()[1].value, json!("forever"));
+    }
+
+    #[test]
+    fn test_query() {
+        let mut db = MooClient::new_dynamic("test_query", None, None).unwrap();
+
+        db.reset_table("test_query").unwrap();
+
+        let accounts = db.get_table().unwrap();
+        let mut accounts = accounts.write().unwrap();
+
+        let rows = [
+            (
+                "1",
+                json!({ "status": "active", "balance": 150, "address": { "city": "Oslo" } }),
+            ),
+            ("2", json!({ "status": "active", "balance": 50.5 })),
+            ("3", json!({ "status": "closed", "balance": 300 })),
+            ("4", json!({ "balance": 100 })),
+        ];
+
+        for (key, value) in rows {
+            accounts.insert(key, value).unwrap();
+        }
+
+        let keys = |records: crate::MooRecords<serde_json::Value>| {
+            records
+                .into_iter()
+                .map(|record| record.key)
+                .collect::<Vec<_>>()
+        };
+
+        let rich = accounts
+            .query()
+            .where_eq("status", json!("active"))
+            .where_gt("balance", json!(100))
+            .run()
+            .unwrap();
+
+        assert_eq!(keys(rich), vec!["1"]);
+
+        let at_least = accounts.query().where_gte("balance", json!(100.0)).run();
+
+        assert_eq!(keys(at_least.unwrap()), vec!["1", "3", "4"]);
+
+        let not_active = accounts.query().where_ne("status", json!("active")).run();
+
+        assert_eq!(keys(not_active.unwrap()), vec!["3", "4"]);
+
+        let in_oslo = accounts
+            .query()
+            .where_eq("address.city", json!("Oslo"))
+            .run();
+
+        assert_eq!(keys(in_oslo.unwrap()), vec!["1"]);
+        assert!(accounts
+            .query()
+            .where_lt("status", json!(1))
+            .run()
+            .unwrap()
+            .is_empty());
+    }
+}
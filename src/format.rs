@@ -0,0 +1,297 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use flate2::{read::GzDecoder, write::GzEncoder};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{Read, Write};
+
+use crate::types::{Compression, SerializationFormat};
+use crate::{MooError, MooErrorCodes, MooResult};
+
+/// The length of the nonce stored in front of an encrypted table file.
+const NONCE_LEN: usize = 12;
+
+/// The bytes every table file with a checksum starts with.
+const CHECKSUM_MAGIC: &[u8; 4] = b"MOOC";
+
+/// The length of the checksum header in front of a table file: the magic bytes, the CRC32 checksum as hex and a newline.
+const CHECKSUM_HEADER_LEN: usize = 13;
+
+/// The bytes in front of the table file contents when it has a schema version.
+const SCHEMA_MAGIC: &[u8; 4] = b"MOOS";
+
+/// Turns table records into the bytes stored on disk and back.
+///
+/// Tables save and load through this trait, so every `SerializationFormat` is handled the same way.
+pub trait Serializer {
+    /// Serialize a `value` into bytes.
+    fn serialize<V>(&self, value: &V) -> MooResult<Vec<u8>>
+    where
+        V: Serialize + ?Sized;
+
+    /// Deserialize a value from `bytes`.
+    fn deserialize<V>(&self, bytes: &[u8]) -> MooResult<V>
+    where
+        V: DeserializeOwned;
+}
+
+impl Serializer for SerializationFormat {
+    fn serialize<V>(&self, value: &V) -> MooResult<Vec<u8>>
+    where
+        V: Serialize + ?Sized,
+    {
+        let bytes = match self {
+            SerializationFormat::Json => serde_json::to_vec(value).map_err(|err| err.to_string()),
+            SerializationFormat::Bincode => {
+                bincode::serialize(value).map_err(|err| err.to_string())
+            }
+            SerializationFormat::MessagePack => {
+                rmp_serde::to_vec_named(value).map_err(|err| err.to_string())
+            }
+        };
+
+        match bytes {
+            Ok(bytes) => Ok(bytes),
+            Err(err) => Err(MooError {
+                code: MooErrorCodes::Error,
+                message: format!("Failed to serialize table records as {:?}. {}", self, err),
+            }),
+        }
+    }
+
+    fn deserialize<V>(&self, bytes: &[u8]) -> MooResult<V>
+    where
+        V: DeserializeOwned,
+    {
+        let value = match self {
+            SerializationFormat::Json => {
+                serde_json::from_slice(bytes).map_err(|err| err.to_string())
+            }
+            SerializationFormat::Bincode => {
+                bincode::deserialize(bytes).map_err(|err| err.to_string())
+            }
+            SerializationFormat::MessagePack => {
+                rmp_serde::from_slice(bytes).map_err(|err| err.to_string())
+            }
+        };
+
+        match value {
+            Ok(value) => Ok(value),
+            Err(err) => Err(MooError {
+                code: MooErrorCodes::Error,
+                message: format!("Failed to parse table file as {:?}. {}", self, err),
+            }),
+        }
+    }
+}
+
+impl Compression {
+    /// Compress the serialized table records before they are written to disk.
+    pub fn compress(self, bytes: &[u8]) -> MooResult<Vec<u8>> {
+        let compressed = match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+
+                encoder.write_all(bytes).and_then(|_| encoder.finish())
+            }
+        };
+
+        match compressed {
+            Ok(compressed) => Ok(compressed),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Error,
+                message: "Failed to compress table records.".to_string(),
+            }),
+        }
+    }
+
+    /// Decompress the contents of a table file before its records are parsed.
+    pub fn decompress(self, bytes: &[u8]) -> MooResult<Vec<u8>> {
+        let mut decompressed = Vec::new();
+
+        let result = match self {
+            Compression::Gzip => GzDecoder::new(bytes).read_to_end(&mut decompressed),
+        };
+
+        match result {
+            Ok(_) => Ok(decompressed),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Error,
+                message: "Failed to decompress table file.".to_string(),
+            }),
+        }
+    }
+}
+
+/// Encrypt the table file contents with AES-256-GCM.
+///
+/// A random nonce is generated for every save and stored in front of the encrypted bytes.
+pub fn encrypt(key: &[u8; 32], bytes: &[u8]) -> MooResult<Vec<u8>> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    match cipher.encrypt(&nonce, bytes) {
+        Ok(encrypted) => {
+            let mut contents = nonce.to_vec();
+            contents.extend(encrypted);
+
+            Ok(contents)
+        }
+        Err(_) => Err(MooError {
+            code: MooErrorCodes::Error,
+            message: "Failed to encrypt table records.".to_string(),
+        }),
+    }
+}
+
+/// Decrypt the contents of a table file written by `encrypt`.
+pub fn decrypt(key: &[u8; 32], contents: &[u8]) -> MooResult<Vec<u8>> {
+    let decrypted = match contents.len() {
+        len if len < NONCE_LEN => None,
+        _ => {
+            let (nonce, encrypted) = contents.split_at(NONCE_LEN);
+            let cipher = Aes256Gcm::new(key.into());
+
+            cipher.decrypt(Nonce::from_slice(nonce), encrypted).ok()
+        }
+    };
+
+    match decrypted {
+        Some(decrypted) => Ok(decrypted),
+        None => Err(MooError {
+            code: MooErrorCodes::Error,
+            message: "Failed to decrypt table file. The encryption key might be wrong or the file isn't encrypted.".to_string(),
+        }),
+    }
+}
+
+/// Put a header line with the CRC32 checksum of the table file contents in front of them.
+///
+/// The header is plain text, so JSON table files stay readable.
+pub fn add_checksum(bytes: Vec<u8>) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(CHECKSUM_HEADER_LEN + bytes.len());
+
+    contents.extend_from_slice(CHECKSUM_MAGIC);
+    contents.extend_from_slice(format!("{:08x}\n", crc32fast::hash(&bytes)).as_bytes());
+    contents.extend(bytes);
+
+    contents
+}
+
+/// Verify the checksum in front of the table file contents and remove it.
+///
+/// Returns a `MooResult` with the contents after the checksum, or `None` for older files written without a checksum.
+/// A checksum that doesn't match returns a `Corrupt` error.
+pub fn strip_checksum(contents: &[u8]) -> MooResult<Option<&[u8]>> {
+    if contents.len() < CHECKSUM_HEADER_LEN || !contents.starts_with(CHECKSUM_MAGIC) {
+        return Ok(None);
+    }
+
+    let (header, bytes) = contents.split_at(CHECKSUM_HEADER_LEN);
+
+    let expected = std::str::from_utf8(&header[CHECKSUM_MAGIC.len()..CHECKSUM_HEADER_LEN - 1])
+        .ok()
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+
+    if expected != Some(crc32fast::hash(bytes)) {
+        return Err(MooError {
+            code: MooErrorCodes::Corrupt,
+            message: "Table file doesn't match its checksum. It might be damaged on disk."
+                .to_string(),
+        });
+    }
+
+    Ok(Some(bytes))
+}
+
+/// Remove the checksum in front of the table file contents without verifying it, to salvage a damaged file.
+pub fn skip_checksum(contents: &[u8]) -> &[u8] {
+    if contents.len() < CHECKSUM_HEADER_LEN || !contents.starts_with(CHECKSUM_MAGIC) {
+        return contents;
+    }
+
+    &contents[CHECKSUM_HEADER_LEN..]
+}
+
+/// Parse the records of a damaged JSON table file one at a time, stopping at the first one that can't be parsed.
+///
+/// Returns a `MooResult` with the recovered records and the number of bytes dropped after them.
+/// Contents that don't start with a JSON array return a `Corrupt` error.
+pub fn recover_json_records(contents: &[u8]) -> MooResult<(Vec<serde_json::Value>, usize)> {
+    let is_separator = |byte: &u8| byte.is_ascii_whitespace() || *byte == b',';
+
+    let start = contents
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .filter(|&start| contents[start] == b'[')
+        .ok_or_else(|| MooError {
+            code: MooErrorCodes::Corrupt,
+            message: "Table file is too damaged to recover any records.".to_string(),
+        })?;
+
+    let mut records = Vec::new();
+    let mut offset = start + 1;
+
+    loop {
+        while contents.get(offset).is_some_and(is_separator) {
+            offset += 1;
+        }
+
+        if contents.get(offset) == Some(&b']') {
+            offset += 1;
+            break;
+        }
+
+        let mut stream = serde_json::Deserializer::from_slice(&contents[offset..])
+            .into_iter::<serde_json::Value>();
+
+        match stream.next() {
+            Some(Ok(record)) => {
+                records.push(record);
+                offset += stream.byte_offset();
+            }
+            _ => break,
+        }
+    }
+
+    Ok((records, contents.len() - offset))
+}
+
+/// Put a header line with the schema version in front of the table file contents.
+///
+/// Version 0 is the default and gets no header, so those files keep the layout they had before schema versions.
+pub fn add_schema_version(bytes: Vec<u8>, version: u32) -> Vec<u8> {
+    if version == 0 {
+        return bytes;
+    }
+
+    let mut contents = SCHEMA_MAGIC.to_vec();
+
+    contents.extend_from_slice(format!("{}\n", version).as_bytes());
+    contents.extend(bytes);
+
+    contents
+}
+
+/// Read the schema version in front of the table file contents and remove it.
+///
+/// Returns a `MooResult` with the schema version and the contents after it, version 0 for files without one.
+pub fn strip_schema_version(contents: &[u8]) -> MooResult<(u32, &[u8])> {
+    let rest = match contents.strip_prefix(SCHEMA_MAGIC) {
+        Some(rest) => rest,
+        None => return Ok((0, contents)),
+    };
+
+    let version = rest.iter().position(|&byte| byte == b'\n').and_then(|end| {
+        let version = std::str::from_utf8(&rest[..end]).ok()?.parse().ok()?;
+
+        Some((version, &rest[end + 1..]))
+    });
+
+    match version {
+        Some(version) => Ok(version),
+        None => Err(MooError {
+            code: MooErrorCodes::Corrupt,
+            message: "Table file has an unreadable schema version.".to_string(),
+        }),
+    }
+}
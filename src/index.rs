@@ -0,0 +1,109 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use ordered_float::OrderedFloat;
+
+/// A range index over a numeric field of the record values.
+///
+/// Record keys are grouped by the extracted number in a `BTreeMap`,
+/// so range queries don't have to scan every record in the table.
+#[derive(Clone)]
+pub struct NumericIndex<K, T> {
+    /// Extracts the indexed number from a record value.
+    extractor: Arc<dyn Fn(&T) -> f64 + Send + Sync>,
+    /// The record keys for every indexed number.
+    entries: BTreeMap<OrderedFloat<f64>, Vec<K>>,
+}
+
+impl<K, T> NumericIndex<K, T>
+where
+    K: Clone + PartialEq,
+{
+    /// Create a new empty index using the `extractor` to read the indexed number from a value.
+    pub fn new<F>(extractor: F) -> Self
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            extractor: Arc::new(extractor),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Add a record to the index.
+    pub fn add(&mut self, key: &K, value: &T) {
+        let number = OrderedFloat((self.extractor)(value));
+
+        self.entries.entry(number).or_default().push(key.clone());
+    }
+
+    /// Remove a record from the index.
+    ///
+    /// The `value` must be the value the record was indexed with.
+    pub fn remove(&mut self, key: &K, value: &T) {
+        let number = OrderedFloat((self.extractor)(value));
+
+        if let Some(keys) = self.entries.get_mut(&number) {
+            keys.retain(|k| k != key);
+
+            if keys.is_empty() {
+                self.entries.remove(&number);
+            }
+        }
+    }
+
+    /// Remove every record from the index.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Get the keys of all records whose indexed number is within the `range`, in ascending order.
+    pub fn range<R>(&self, range: R) -> Vec<&K>
+    where
+        R: RangeBounds<f64>,
+    {
+        let start = to_ordered(range.start_bound());
+        let end = to_ordered(range.end_bound());
+
+        // BTreeMap::range panics on inverted or empty exclusive ranges, those simply match nothing.
+        if let (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) =
+            (start, end)
+        {
+            let both_excluded =
+                matches!(start, Bound::Excluded(_)) && matches!(end, Bound::Excluded(_));
+
+            if s > e || (s == e && both_excluded) {
+                return Vec::new();
+            }
+        }
+
+        self.entries
+            .range((start, end))
+            .flat_map(|(_, keys)| keys.iter())
+            .collect()
+    }
+}
+
+impl<K, T> Debug for NumericIndex<K, T>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NumericIndex")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+/// Convert a float bound into an ordered float bound.
+fn to_ordered(bound: Bound<&f64>) -> Bound<OrderedFloat<f64>> {
+    match bound {
+        Bound::Included(n) => Bound::Included(OrderedFloat(*n)),
+        Bound::Excluded(n) => Bound::Excluded(OrderedFloat(*n)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
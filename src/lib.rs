@@ -6,10 +6,15 @@
 //!
 //! - **Simple**: MooDB is simple to use and easy to learn.
 //! - **Fast**: MooDB is fast and efficient using memory and disk API's.
-//! - **File Persistence**: MooDB uses the filesystem to store data in a simple JSON format.
+//! - **File Persistence**: MooDB uses the filesystem to store data in a simple JSON format, or in bincode or MessagePack.
 //! - **Key-Value**: MooDB is a key-value database.
 //! - **Rust**: MooDB is written in Rust.
-//! - **Thread Safe**: MooDB is thread safe by default.
+//! - **Thread Safe**: MooDB is thread safe by default. Access to a table file is serialized by a lock, while tables shared between threads with `into_shared` read their records concurrently.
+//!
+//! ## Cargo Features
+//!
+//! - `arc-values`: Adds the `ArcMooClient` and `ArcMooTable` aliases that store values behind an `Arc`, so reads don't deep copy them.
+//! - `tokio`: Adds `async_core::AsyncMooTable`, an async table whose disk writes use `tokio::fs`. Create one with `into_async`.
 //!
 //! ## Design
 //!
@@ -20,7 +25,7 @@
 //! ```
 //! use moodb::core::MooClient;
 //! use serde::{Deserialize, Serialize};
-//! 
+//!
 //! #[derive(Clone, Serialize, Deserialize, Debug)]
 //! struct Bank {
 //!     balance: f32,
@@ -28,9 +33,10 @@
 //! }
 //!
 //! fn main() {
-//!     let mut db = MooClient::<String>::new("test", None, None).unwrap();
+//!     let mut db = MooClient::<Bank>::new("bank_accounts", None, None).unwrap();
 //!
-//!     let mut user_accounts = db.get_table().unwrap();
+//!     let table = db.get_table().unwrap();
+//!     let mut user_accounts = table.write().unwrap();
 //!
 //!     user_accounts.insert("1", Bank {
 //!        balance: 100.0,
@@ -41,11 +47,19 @@
 //!
 //!     println!("User: {:?}", user);
 //!
-//!     db.delete_table().unwrap();
+//!     drop(user_accounts);
+//!
+//!     db.delete_table("bank_accounts").unwrap();
 //! }
 //!
 //! ```
 //!
+//! Schemaless tables, whose records hold any `serde_json::Value`, are created with `MooClient::new_dynamic`
+//! and stored and read with `insert_value` and `get_value`. Their records can be filtered by field with `query`.
+//!
+//! Records are keyed by `String` by default. Any other `MooKey` type can be used instead,
+//! for example `MooClient::<Bank, u64>` stores its records under numeric ids.
+//!
 //! MooDB main functionality comes from using the built-in methods on a table for your database.
 //!
 //! These methods are:
@@ -53,89 +67,588 @@
 //! Client:
 //!
 //! - `new`: Creates a new client for the database.
-//! - `get_table`: Gets a table from the database.
+//! - `get_table`: Gets a handle to the table bound to the client, shared with every other handle.
+//! - `into_table`: Takes the table out of the client.
+//! - `create_table`: Creates another table in the database.
+//! - `open_table`: Opens another existing table in the database.
+//! - `list_tables`: Lists all tables in the database.
 //! - `reset_table`: Resets a table in the database. (Keeps the db file.)
 //! - `delete_table`: Deletes a table from the database. (Deletes the db file.)
+//! - `reset_all_tables`: Resets every table in the database.
+//! - `delete_all_tables`: Deletes every table file in the database.
+//! - `total_size`: Gets the size of all table files in the database.
 //!
 //! Table:
 //!
 //! - `insert`: Inserts a record into the table.
+//! - `try_insert`: Inserts a record unless its key already exists, without an error.
 //! - `insert_many`: Inserts many records into the table.
+//! - `insert_with_ttl`: Inserts a record that expires after a while.
+//! - `purge_expired`: Deletes all expired records from the table.
+//! - `restore` / `purge_deleted`: Brings back or permanently removes soft deleted records.
 //! - `get`: Gets a record from the table.
 //! - `get_many`: Gets many records from the table.
+//! - `get_many_checked`: Gets many records from the table and reports the missing keys.
+//! - `get_or_insert_with`: Gets a record or inserts a new one if it's missing.
+//! - `get_or` / `get_or_default`: Gets a record or a fallback value without inserting it.
 //! - `get_all`: Gets all records from the table.
+//! - `get_page`: Gets one page of records from the table.
+//! - `keys`: Iterates over the keys of the records.
+//! - `values`: Iterates over the values of the records.
+//! - `for_each`: Calls a closure with every record without cloning them.
+//! - `iter_sorted`: Iterates over the records in key order.
+//! - `first` / `last`: Gets the record with the smallest or largest key.
+//! - `range`: Gets the records whose key falls within a range, in key order.
+//! - `find`: Finds all records matching a predicate.
+//! - `find_one`: Finds the first record matching a predicate.
+//! - `scan_prefix`: Finds all records whose key starts with a prefix.
+//! - `scan_pattern`: Finds all records whose key matches a glob pattern like `user:*:settings`.
+//! - `count_where`: Counts the records matching a predicate.
+//! - `count_matching`: Counts the records a bulk delete with the same predicate would affect.
+//! - `contains_key`: Checks if a record is in the table.
+//! - `which_exist` / `which_missing`: Splits a list of keys into the ones in the table and the ones that aren't.
+//! - `len`: Gets the number of records in the table.
+//! - `count`: Gets the number of records in the table, without an error when it's empty.
+//! - `is_empty`: Checks if the table has no records.
+//! - `size_on_disk`: Gets the size of the table file.
+//! - `metrics`: Gets the operation counts of the table and the time spent saving it.
 //! - `delete`: Deletes a record from the table.
 //! - `delete_many`: Deletes many records from the table.
+//! - `retain`: Deletes every record not matching a predicate.
+//! - `delete_many_with` / `retain_with`: Bulk deletes that only count the affected records with `RunMode::DryRun`.
 //! - `delete_all`: Deletes all records from the table.
+//! - `clear_memory`: Removes all records from memory, keeping the table file until the next save.
+//! - `reload_from_disk`: Replaces the records in memory with the ones in the table file.
 //! - `update`: Updates a record in the table.
 //! - `update_many`: Updates many records in the table.
+//! - `replace_all`: Replaces every record in the table with a single write.
+//! - `set_validator`: Rejects inserted or updated records that fail a check.
+//! - `modify`: Changes the value of a record in place with a closure.
+//! - `entry`: Borrows the value of a record mutably and saves it when the guard is dropped.
+//! - `rename_key`: Renames the key of a record.
+//! - `increment` / `decrement`: Adds to or subtracts from a numeric record.
+//! - `get_by_u64` / `insert_u64` / `keys_as_u64`: Work with numeric keys stored as strings.
+//! - `upsert`: Inserts a record or updates it if it already exists.
+//! - `merge`: Combines an operand with the value of a record, creating it if it is missing.
+//! - `upsert_many`: Inserts or updates many records in the table and reports how many of each.
+//! - `flush`: Saves in-memory changes to disk when autosave is disabled.
+//! - `compact` / `compact_sorted`: Rewrites the table file from the current records, optionally sorted by key.
+//! - `batch`: Applies many inserts, updates and deletes with a single save.
+//! - `as_readonly`: Borrows the table as a handle that can only read it.
+//! - `export`: Writes all records as JSON to any writer.
+//! - `export_csv`: Writes the records as CSV rows with a header, to open them in a spreadsheet.
+//! - `import`: Reads records written by `export` into the table.
+//! - `import_json_map`: Inserts the entries of a JSON object mapping keys to values.
+//! - `to_json_string` / `from_json_string`: Dumps the records to a JSON string and loads them back.
+//! - `snapshot` / `restore_snapshot`: Takes a point-in-time backup of the table and rolls back to it.
+//! - `list_snapshots` / `delete_snapshot`: Lists or deletes the snapshots of the table.
 //!
 //! You can find more detailed information in the core module documentation.
 //!
 
 ////////////////////////////////////////////////////////////////////////////////
 
-use serde::{Deserialize, Serialize};
-use utils::debug::DebugLevel;
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug},
+    fs,
+    hash::Hash,
+    io,
+    path::{Path, PathBuf},
+};
+use types::{Compression, ConflictPolicy, SerializationFormat, StorageTypes};
+use utils::debug::{DebugFormat, DebugLevel};
 
+#[cfg(feature = "tokio")]
+pub mod async_core;
 pub mod core;
+pub mod dynamic;
+pub mod format;
+mod index;
+pub mod ring;
+pub mod types;
 mod utils;
 
-const FILE_EXTENSION: &str = "json";
 const DEFAULT_DIR: &str = "db/moo";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 /// Configuration for the database.
 pub struct Configuration {
-    /// The directory to store the database file.
-    pub db_dir: &'static str,
+    /// The directory to store the database file. It can be built at runtime, like from an environment variable.
+    pub db_dir: PathBuf,
     /// Whether or not to enable debug mode for the database.
     pub debug_mode: bool,
     /// The debug level for the database.
     pub debug_level: Option<DebugLevel>,
+    /// How each message is written to the debug file, as text or as a JSON object per line.
+    pub debug_format: DebugFormat,
+    /// What to do when a table file was changed by another handle before saving.
+    pub conflict_policy: ConflictPolicy,
+    /// Fold the write-ahead log into the table file after a save once the files on disk are more than this many times
+    /// the size of the table file. Only applies with `wal` enabled, since every other save rewrites the whole table file.
+    /// The ratio must be greater than 1.
+    pub auto_compact_ratio: Option<f32>,
+    /// Whether to mirror every change on a table to an append-only `<name>.audit.jsonl` file next to it.
+    /// Memory tables never touch disk, so they don't have an audit log.
+    pub audit_log: bool,
+    /// How the tables store their records. `StorageTypes::Memory` tables are never written to disk.
+    pub storage: StorageTypes,
+    /// The format new table files are written in. Existing table files keep the format they were created in.
+    pub format: SerializationFormat,
+    /// Compress new table files on disk. Existing table files are detected by their extension and keep their compression.
+    pub compression: Option<Compression>,
+    /// The extension of table files, without the leading dot. By default it's derived from `format`, like `json`.
+    /// With an override the format can't be told from the file name, so every table file is read in `format`.
+    pub file_extension_override: Option<String>,
+    /// A prefix put in front of the name of every table file, like `moo_` for `moo_<name>.json`.
+    /// Files without the prefix aren't treated as tables, which avoids collisions with files of other tools.
+    pub file_prefix: Option<String>,
+    /// Whether `delete` only marks records as deleted instead of removing them, so they can be brought back with
+    /// `MooTable::restore`. Deleted records can't be read, and stay in the table file until `purge_deleted` is called.
+    pub soft_delete: bool,
+    /// Whether JSON table files are written indented over multiple lines, which is easier to read and diff by hand.
+    /// Table files are read the same way either way, so this can be changed at any time.
+    pub pretty_json: bool,
+    /// Whether a damaged JSON table file is salvaged instead of failing to open, like one with garbage after the
+    /// records or cut off by a crash. Every record before the damage is kept and the rest is dropped with a warning.
+    /// The next save rewrites the table file with only the recovered records, so keep a copy if the rest matters.
+    pub recover_corrupt: bool,
+    /// Whether every change is saved to disk right away. When disabled, changes only live in memory
+    /// until `MooTable::flush` is called or the table is dropped, which makes large imports much faster.
+    pub autosave: bool,
+    /// Whether saving a table appends the changed records to a `<name>.wal` file next to it instead of rewriting
+    /// the whole table file. The log is replayed when the table is opened and folded into the table file by `compact`,
+    /// or automatically once `auto_compact_ratio` is reached.
+    pub wal: bool,
+    /// The size in bytes the `debug.log` file can grow to before it is rotated to `debug.log.1`.
+    /// Only the last rotated file is kept. Without a limit the log grows forever.
+    pub max_log_size: Option<u64>,
+    /// Whether debug messages are sent to the `log` crate instead of the `debug.log` file,
+    /// so they end up in the logger of the application, like `env_logger`.
+    pub use_log_crate: bool,
+    /// Whether a table takes an exclusive lock on a `<name>.lock` file next to it while it's open,
+    /// so another process or client can't open the same table and overwrite its changes.
+    /// The handles returned by `get_table` all share the one table of their client, and with it its lock.
+    pub lock_tables: bool,
+    /// Encrypt table files with AES-256-GCM using this key. Without a key, table files are stored as plain text.
+    /// Only the table files are encrypted, the write-ahead log, the audit log and exports are not.
+    pub encryption_key: Option<[u8; 32]>,
+    /// The schema version of the record values, written into every saved table file.
+    /// Bump it when the shape of the values changes, so table files with an older version are upgraded by `migrate`.
+    pub schema_version: u32,
+    /// Upgrades the value of every record in a table file with an older `schema_version` when the table is loaded.
+    /// It gets the schema version of the file and the record value as JSON, and returns the value in the current shape.
+    /// Bincode isn't self-describing, so bincode table files can't be migrated.
+    #[serde(skip)]
+    pub migrate: Option<fn(u32, serde_json::Value) -> serde_json::Value>,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Self {
-            db_dir: DEFAULT_DIR,
+            db_dir: PathBuf::from(DEFAULT_DIR),
             debug_mode: false,
             debug_level: Some(DebugLevel::Info),
+            debug_format: DebugFormat::Text,
+            conflict_policy: ConflictPolicy::Overwrite,
+            auto_compact_ratio: None,
+            audit_log: false,
+            storage: StorageTypes::Json,
+            format: SerializationFormat::Json,
+            compression: None,
+            file_extension_override: None,
+            file_prefix: None,
+            soft_delete: false,
+            pretty_json: false,
+            recover_corrupt: false,
+            autosave: true,
+            wal: false,
+            max_log_size: None,
+            use_log_crate: false,
+            lock_tables: false,
+            encryption_key: None,
+            schema_version: 0,
+            migrate: None,
+        }
+    }
+}
+
+impl Configuration {
+    /// Start building a configuration from the defaults, setting only the fields that should differ.
+    ///
+    /// Returns a `ConfigurationBuilder` to chain the settings on.
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::default()
+    }
+
+    /// Load the configuration from a file.
+    ///
+    /// The `path` to a `.toml` or `.json` config file. Files with any other extension are read as JSON.
+    ///
+    /// Fields missing from the file keep their default value.
+    ///
+    /// Returns a `MooResult` with the loaded configuration.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> MooResult<Configuration> {
+        let path = path.as_ref();
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                return Err(MooError {
+                    code: MooErrorCodes::Error,
+                    message: format!("Failed to read config file: {}", path.display()),
+                })
+            }
+        };
+
+        let is_toml = path.extension().is_some_and(|ext| ext == "toml");
+
+        let config = if is_toml {
+            toml::from_str(&contents).map_err(|err| err.to_string())
+        } else {
+            serde_json::from_str(&contents).map_err(|err| err.to_string())
+        };
+
+        match config {
+            Ok(config) => Ok(config),
+            Err(err) => Err(MooError {
+                code: MooErrorCodes::Error,
+                message: format!("Failed to parse config file: {}. {}", path.display(), err),
+            }),
         }
     }
 }
 
+/// A builder for `Configuration`, starting from the default configuration.
+///
+/// Every method sets one field and returns the builder, so the settings can be chained before calling `build`.
+/// Fields that are never set keep their default value, so new fields don't break existing builders.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigurationBuilder {
+    config: Configuration,
+}
+
+impl ConfigurationBuilder {
+    /// The directory to store the database files in.
+    pub fn db_dir(mut self, db_dir: impl Into<PathBuf>) -> Self {
+        self.config.db_dir = db_dir.into();
+        self
+    }
+
+    /// Whether or not to enable debug mode for the database.
+    pub fn debug(mut self, debug_mode: bool) -> Self {
+        self.config.debug_mode = debug_mode;
+        self
+    }
+
+    /// The debug level for the database.
+    pub fn debug_level(mut self, debug_level: DebugLevel) -> Self {
+        self.config.debug_level = Some(debug_level);
+        self
+    }
+
+    /// How each message is written to the debug file.
+    pub fn debug_format(mut self, debug_format: DebugFormat) -> Self {
+        self.config.debug_format = debug_format;
+        self
+    }
+
+    /// What to do when a table file was changed by another handle before saving.
+    pub fn conflict_policy(mut self, conflict_policy: ConflictPolicy) -> Self {
+        self.config.conflict_policy = conflict_policy;
+        self
+    }
+
+    /// Fold the write-ahead log into the table file once the files on disk are more than this many times its size.
+    pub fn auto_compact_ratio(mut self, ratio: f32) -> Self {
+        self.config.auto_compact_ratio = Some(ratio);
+        self
+    }
+
+    /// Whether to mirror every change on a table to an append-only audit log.
+    pub fn audit_log(mut self, audit_log: bool) -> Self {
+        self.config.audit_log = audit_log;
+        self
+    }
+
+    /// How the tables store their records.
+    pub fn storage(mut self, storage: StorageTypes) -> Self {
+        self.config.storage = storage;
+        self
+    }
+
+    /// The format new table files are written in.
+    pub fn format(mut self, format: SerializationFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+
+    /// Compress new table files on disk.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    /// The extension of table files, instead of the one derived from the format.
+    pub fn file_extension(mut self, extension: impl Into<String>) -> Self {
+        self.config.file_extension_override = Some(extension.into());
+        self
+    }
+
+    /// A prefix put in front of the name of every table file.
+    pub fn file_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.file_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Whether `delete` only marks records as deleted instead of removing them.
+    pub fn soft_delete(mut self, soft_delete: bool) -> Self {
+        self.config.soft_delete = soft_delete;
+        self
+    }
+
+    /// Whether JSON table files are written indented over multiple lines.
+    pub fn pretty_json(mut self, pretty_json: bool) -> Self {
+        self.config.pretty_json = pretty_json;
+        self
+    }
+
+    /// Whether a damaged JSON table file is salvaged instead of failing to open.
+    pub fn recover_corrupt(mut self, recover_corrupt: bool) -> Self {
+        self.config.recover_corrupt = recover_corrupt;
+        self
+    }
+
+    /// Whether every change is saved to disk right away.
+    pub fn autosave(mut self, autosave: bool) -> Self {
+        self.config.autosave = autosave;
+        self
+    }
+
+    /// Whether saving a table appends the changed records to a write-ahead log.
+    pub fn wal(mut self, wal: bool) -> Self {
+        self.config.wal = wal;
+        self
+    }
+
+    /// The size in bytes the debug file can grow to before it is rotated.
+    pub fn max_log_size(mut self, max_log_size: u64) -> Self {
+        self.config.max_log_size = Some(max_log_size);
+        self
+    }
+
+    /// Whether debug messages are sent to the `log` crate instead of the debug file.
+    pub fn use_log_crate(mut self, use_log_crate: bool) -> Self {
+        self.config.use_log_crate = use_log_crate;
+        self
+    }
+
+    /// Whether a table takes an exclusive lock file while it's open.
+    pub fn lock_tables(mut self, lock_tables: bool) -> Self {
+        self.config.lock_tables = lock_tables;
+        self
+    }
+
+    /// Encrypt table files with AES-256-GCM using this key.
+    pub fn encryption_key(mut self, encryption_key: [u8; 32]) -> Self {
+        self.config.encryption_key = Some(encryption_key);
+        self
+    }
+
+    /// The schema version of the record values, written into every saved table file.
+    pub fn schema_version(mut self, schema_version: u32) -> Self {
+        self.config.schema_version = schema_version;
+        self
+    }
+
+    /// Upgrades the value of every record in a table file with an older schema version when the table is loaded.
+    pub fn migrate(mut self, migrate: fn(u32, serde_json::Value) -> serde_json::Value) -> Self {
+        self.config.migrate = Some(migrate);
+        self
+    }
+
+    /// Finish building the configuration.
+    ///
+    /// Returns the `Configuration` to pass to a `MooClient`.
+    pub fn build(self) -> Configuration {
+        self.config
+    }
+}
+
 /// Return Type for common db actions
 pub type MooResult<T> = Result<T, MooError>;
-pub type MooRecords<T> = Vec<MooRecord<T>>;
+pub type MooRecords<T, K = String> = Vec<MooRecord<T, K>>;
+
+/// The type of the keys records are stored under.
+///
+/// This is implemented for every type that can be hashed, compared and serialized, like `String` or `u64`.
+/// Keys are written to the table file as their own type, so numeric keys are stored as numbers.
+pub trait MooKey: Eq + Hash + Clone + Debug + Serialize + DeserializeOwned {}
+
+impl<K> MooKey for K where K: Eq + Hash + Clone + Debug + Serialize + DeserializeOwned {}
+
+/// A numeric value that `MooTable::increment` and `MooTable::decrement` can change.
+///
+/// This is implemented for every integer type, where going past the bounds of the type fails instead of wrapping,
+/// and for floats, where a result that isn't finite fails.
+pub trait Counter: Copy {
+    /// Adds `by` to the value, or `None` if the result doesn't fit.
+    fn checked_add(self, by: Self) -> Option<Self>;
+
+    /// Subtracts `by` from the value, or `None` if the result doesn't fit.
+    fn checked_sub(self, by: Self) -> Option<Self>;
+}
+
+macro_rules! impl_counter_int {
+    ($($ty:ty),*) => {$(
+        impl Counter for $ty {
+            fn checked_add(self, by: Self) -> Option<Self> {
+                <$ty>::checked_add(self, by)
+            }
+
+            fn checked_sub(self, by: Self) -> Option<Self> {
+                <$ty>::checked_sub(self, by)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_counter_float {
+    ($($ty:ty),*) => {$(
+        impl Counter for $ty {
+            fn checked_add(self, by: Self) -> Option<Self> {
+                Some(self + by).filter(|value| value.is_finite())
+            }
+
+            fn checked_sub(self, by: Self) -> Option<Self> {
+                Some(self - by).filter(|value| value.is_finite())
+            }
+        }
+    )*};
+}
+
+impl_counter_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_counter_float!(f32, f64);
 
 /// A record in the database.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct MooRecord<T>
+pub struct MooRecord<T, K = String>
 where
     T: Serialize,
 {
     /// The Key of the record and how data will be accessed.
-    pub key: String,
+    pub key: K,
     /// The value of the record.
     pub value: T,
+    /// When the record expires in milliseconds since the Unix epoch, or `None` if it never expires.
+    /// Records saved before expiry was supported don't have this field and never expire.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Whether the record was deleted while `Configuration::soft_delete` is enabled.
+    /// Deleted records stay in the table file until they are restored or purged, but can't be read.
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+impl<T, K> MooRecord<T, K>
+where
+    T: Serialize,
+    K: MooKey,
+{
+    /// Creates a new record after checking that its value can be serialized.
+    ///
+    /// Some types only fail to serialize at runtime (for example maps with non-string keys),
+    /// so this catches the problem for a single record instead of failing the whole table save later.
+    ///
+    /// Returns a `MooResult` with the new record.
+    pub fn try_new(key: impl Into<K>, value: T) -> MooResult<Self> {
+        let key = key.into();
+
+        if let Err(err) = serde_json::to_value(&value) {
+            return Err(MooError {
+                code: MooErrorCodes::Error,
+                message: format!("Failed to serialize value for key: {:?}. {}", key, err),
+            });
+        }
+
+        Ok(Self {
+            key,
+            value,
+            expires_at: None,
+            deleted: false,
+        })
+    }
+
+    /// Whether the record has an expiry time that has passed.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now().timestamp_millis())
+    }
+
+    /// Whether the record can be read, because it hasn't expired or been soft deleted.
+    pub fn is_live(&self) -> bool {
+        !self.deleted && !self.is_expired()
+    }
+}
+
+/// A change made to a table.
+///
+/// Change events are sent to every subscriber of a table after the change has been saved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeEvent<T, K = String> {
+    /// A new record was inserted.
+    Inserted { key: K, value: T },
+    /// An existing record was given a new value.
+    Updated { key: K, value: T },
+    /// A record was removed from the table.
+    Deleted { key: K },
 }
 
 /// The error struct for the database.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MooError {
     pub code: MooErrorCodes,
     pub message: String,
 }
 
 /// The error types for the database.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MooErrorCodes {
     NotFound,
     Debug,
     Warn,
     Error,
     Fatal,
+    /// A table file doesn't match its checksum, it was damaged on disk.
+    Corrupt,
+}
+
+impl fmt::Display for MooError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for MooError {}
+
+impl From<io::Error> for MooError {
+    fn from(err: io::Error) -> Self {
+        Self {
+            code: MooErrorCodes::Fatal,
+            message: format!("Failed to access a database file. {}", err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for MooError {
+    fn from(err: serde_json::Error) -> Self {
+        Self {
+            code: MooErrorCodes::Error,
+            message: format!("Failed to serialize or parse JSON. {}", err),
+        }
+    }
 }
 
 mod tests {
@@ -144,6 +657,8 @@ mod tests {
     use crate::Configuration;
     #[allow(unused_imports)]
     use crate::{core::MooClient, utils::debug::DebugLevel};
+    #[allow(unused_imports)]
+    use std::path::PathBuf;
 
     #[test]
     fn insert() {
@@ -151,16 +666,18 @@ mod tests {
             "test",
             None,
             Some(Configuration {
-                db_dir: "db/moo",
+                db_dir: PathBuf::from("db/moo"),
                 debug_mode: true,
                 debug_level: Some(DebugLevel::Info),
+                ..Configuration::default()
             }),
         )
         .unwrap();
 
-        db.reset_table().unwrap();
+        db.reset_table("test").unwrap();
 
-        let mut people = db.get_table().unwrap();
+        let people = db.get_table().unwrap();
+        let mut people = people.write().unwrap();
 
         people.insert("1", "John".to_string()).unwrap();
 
@@ -176,6 +693,108 @@ mod tests {
 
         assert_eq!(people.records.len(), 1);
 
-        // db.delete_table().unwrap();
+        // db.delete_table("test").unwrap();
+    }
+
+    #[test]
+    fn config_builder() {
+        use crate::types::StorageTypes;
+
+        let config = Configuration::builder()
+            .db_dir(PathBuf::from("db").join("builder"))
+            .debug(true)
+            .debug_level(DebugLevel::Warning)
+            .storage(StorageTypes::Memory)
+            .autosave(false)
+            .build();
+
+        assert_eq!(config.db_dir, PathBuf::from("db/builder"));
+        assert!(config.debug_mode);
+        assert_eq!(config.debug_level, Some(DebugLevel::Warning));
+        assert_eq!(config.storage, StorageTypes::Memory);
+        assert!(!config.autosave);
+        assert!(!config.wal);
+
+        let mut db = MooClient::<String>::new("config_builder", None, Some(config)).unwrap();
+
+        db.get_table()
+            .unwrap()
+            .write()
+            .unwrap()
+            .insert("1", "John".to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn config_from_file() {
+        use crate::types::ConflictPolicy;
+        use std::fs;
+
+        fs::create_dir_all("db/config").unwrap();
+
+        fs::write(
+            "db/config/moo.toml",
+            "db_dir = \"db/config\"\ndebug_mode = true\nconflict_policy = \"Abort\"\n",
+        )
+        .unwrap();
+
+        let config = Configuration::from_file("db/config/moo.toml").unwrap();
+
+        assert_eq!(config.db_dir, PathBuf::from("db/config"));
+        assert!(config.debug_mode);
+        assert_eq!(config.conflict_policy, ConflictPolicy::Abort);
+
+        fs::write("db/config/moo.json", r#"{ "debug_level": "Error" }"#).unwrap();
+
+        let config = Configuration::from_file("db/config/moo.json").unwrap();
+
+        assert_eq!(config.db_dir, PathBuf::from("db/moo"));
+        assert!(!config.debug_mode);
+
+        let mut db =
+            MooClient::<String>::from_config_file("config_test", "db/config/moo.toml").unwrap();
+
+        assert!(db.path.ends_with("db/config"));
+
+        db.delete_table("config_test").unwrap();
+    }
+
+    #[test]
+    fn error_display() {
+        use crate::{MooError, MooErrorCodes};
+        use std::error::Error;
+
+        fn open_missing() -> Result<(), Box<dyn Error>> {
+            std::fs::read("db/missing/file.json").map_err(MooError::from)?;
+            Ok(())
+        }
+
+        let err = MooError {
+            code: MooErrorCodes::NotFound,
+            message: "No record found with key: \"1\"".to_string(),
+        };
+
+        assert_eq!(err.to_string(), "NotFound: No record found with key: \"1\"");
+        assert!(open_missing()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Fatal: "));
+
+        let parse_err: MooError = serde_json::from_str::<u32>("moo").unwrap_err().into();
+
+        assert_eq!(parse_err.code, MooErrorCodes::Error);
+    }
+
+    #[test]
+    fn record_try_new() {
+        use crate::MooRecord;
+        use std::collections::HashMap;
+
+        assert!(MooRecord::<String>::try_new("1", "John".to_string()).is_ok());
+
+        let mut bad = HashMap::new();
+        bad.insert((1, 2), "tuple keys can't be json object keys".to_string());
+
+        assert!(MooRecord::<_>::try_new("2", bad).is_err());
     }
 }
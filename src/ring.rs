@@ -0,0 +1,164 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use crate::{MooError, MooErrorCodes, MooRecord, MooRecords, MooResult};
+
+/// A fixed capacity table that only lives in memory.
+///
+/// Once the table is full, inserting a new record evicts the oldest one.
+/// Nothing is ever written to disk, so this is useful as a bounded buffer
+/// of recent events like the last N log lines or metric samples.
+#[derive(Debug, Clone)]
+pub struct MooRingTable<T>
+where
+    T: Clone + Serialize,
+{
+    pub name: String,
+    /// The records in the table, oldest first.
+    records: VecDeque<MooRecord<T>>,
+    /// The maximum number of records kept in the table.
+    capacity: usize,
+}
+
+impl<T> MooRingTable<T>
+where
+    T: Clone + Serialize,
+{
+    /// Creates a new ring table.
+    ///
+    /// The `name` of the table.
+    ///
+    /// The `capacity` of the table, it must be greater than zero.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn new(name: &str, capacity: usize) -> MooResult<MooRingTable<T>> {
+        if capacity == 0 {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: "Ring table capacity must be greater than zero.".to_string(),
+            });
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+        })
+    }
+
+    /// Insert a new record into the table, evicting the oldest record if the table is full.
+    ///
+    /// The `key` of the record to insert.
+    ///
+    /// The `value` of the record to insert.
+    ///
+    /// Returns a `MooResult` with the evicted record, if any.
+    pub fn insert(&mut self, key: &str, value: T) -> MooResult<Option<MooRecord<T>>> {
+        if self.contains_key(key) {
+            return Err(MooError {
+                code: MooErrorCodes::Warn,
+                message: format!("Record with key: {} already exists.", key),
+            });
+        }
+
+        let evicted = if self.records.len() == self.capacity {
+            self.records.pop_front()
+        } else {
+            None
+        };
+
+        self.records.push_back(MooRecord {
+            key: key.to_string(),
+            value,
+            expires_at: None,
+            deleted: false,
+        });
+
+        Ok(evicted)
+    }
+
+    /// Get a record from the table.
+    ///
+    /// The `key` of the record to get.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn get(&self, key: &str) -> MooResult<T> {
+        match self.records.iter().find(|record| record.key == key) {
+            Some(record) => Ok(record.value.clone()),
+            None => Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No record found with key: {}", key),
+            }),
+        }
+    }
+
+    /// Get all the records from the table, oldest first.
+    pub fn get_all(&self) -> MooRecords<T> {
+        self.records.iter().cloned().collect()
+    }
+
+    /// Delete a record from the table.
+    ///
+    /// The `key` of the record to delete.
+    ///
+    /// Returns a `MooResult` with the result of the action.
+    pub fn delete(&mut self, key: &str) -> MooResult<()> {
+        match self.records.iter().position(|record| record.key == key) {
+            Some(index) => {
+                self.records.remove(index);
+                Ok(())
+            }
+            None => Err(MooError {
+                code: MooErrorCodes::NotFound,
+                message: format!("No record found with key: {}", key),
+            }),
+        }
+    }
+
+    /// Deletes all the records from the table.
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// Whether a record with the `key` is in the table.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.records.iter().any(|record| record.key == key)
+    }
+
+    /// The number of records in the table.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the table has no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The maximum number of records kept in the table.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MooRingTable;
+
+    #[test]
+    fn test_ring_eviction() {
+        let mut samples = MooRingTable::<u32>::new("samples", 3).unwrap();
+
+        for i in 0..3 {
+            assert!(samples.insert(&i.to_string(), i).unwrap().is_none());
+        }
+
+        let evicted = samples.insert("3", 3).unwrap().unwrap();
+
+        assert_eq!(evicted.key, "0");
+        assert_eq!(samples.len(), 3);
+        assert!(samples.get("0").is_err());
+        assert_eq!(samples.get_all()[0].key, "1");
+        assert!(MooRingTable::<u32>::new("empty", 0).is_err());
+    }
+}
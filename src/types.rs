@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use std::time::{Duration, SystemTime};
+
+/// The ways a table can store its records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum StorageTypes {
+    /// Records only live in memory and are never written to disk.
+    Memory,
+    /// Records are stored in a JSON file.
+    #[default]
+    Json,
+}
+
+/// The formats a table file can be serialized in.
+///
+/// The table file gets the extension of its format, so tables in different formats can share a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum SerializationFormat {
+    /// Human readable JSON, stored in a `.json` file.
+    #[default]
+    Json,
+    /// Compact binary bincode, stored in a `.bin` file.
+    /// Bincode isn't self-describing, so values that need `deserialize_any` (like `serde_json::Value`) can't be stored.
+    Bincode,
+    /// Compact binary MessagePack, stored in a `.msgpack` file.
+    MessagePack,
+}
+
+impl SerializationFormat {
+    /// Every serialization format, in the order they are looked for when opening a table.
+    pub const ALL: [SerializationFormat; 3] = [
+        SerializationFormat::Json,
+        SerializationFormat::Bincode,
+        SerializationFormat::MessagePack,
+    ];
+
+    /// The extension of table files in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            SerializationFormat::Json => "json",
+            SerializationFormat::Bincode => "bin",
+            SerializationFormat::MessagePack => "msgpack",
+        }
+    }
+
+    /// Gets the format of a table file from its extension, or `None` if it isn't a table file.
+    pub fn from_extension(extension: &str) -> Option<SerializationFormat> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// The ways a table file can be compressed on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Compression {
+    /// Gzip compression, the table file gets an extra `.gz` extension.
+    Gzip,
+}
+
+impl Compression {
+    /// The extra extension of table files with this compression.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+        }
+    }
+}
+
+/// How imported records are combined with the records already in a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// Delete every record in the table and keep only the imported records.
+    Replace,
+    /// Insert the imported records, updating the records with the same key.
+    #[default]
+    Merge,
+}
+
+/// Whether a bulk delete like `MooTable::retain_with` changes the table or only counts what it would change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunMode {
+    /// Delete the records and save the table.
+    #[default]
+    Apply,
+    /// Only count the records that would be deleted, without changing or saving anything.
+    DryRun,
+}
+
+/// What a table does when it saves and finds its file was changed by another handle
+/// since this table last loaded or saved it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum ConflictPolicy {
+    /// Overwrite the file with this table's records, the other changes are lost.
+    #[default]
+    Overwrite,
+    /// Don't save and return an error, the unsaved changes stay in memory.
+    Abort,
+    /// Keep the records this table changed and take every other record from the file.
+    MergeNewest,
+}
+
+/// What a bulk write did with its records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchReport {
+    /// The number of records that were inserted with a new key.
+    pub inserted: usize,
+    /// The number of records that replaced the value of an existing key.
+    pub updated: usize,
+}
+
+/// Counts of the operations on a table and the time spent saving it, since the table was opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableMetrics {
+    /// The number of lookups of records by key.
+    pub gets: u64,
+    /// The number of records inserted.
+    pub inserts: u64,
+    /// The number of records whose value was updated.
+    pub updates: u64,
+    /// The number of records deleted, including expired records that were removed.
+    pub deletes: u64,
+    /// The number of times the changes were written to disk.
+    pub saves: u64,
+    /// The time spent writing changes to disk, added up over all saves.
+    pub save_duration: Duration,
+}
+
+/// Metrics about a single table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    /// The number of records in the table.
+    pub records: usize,
+    /// The size of the table file in bytes.
+    pub size: u64,
+    /// When the table file was last modified, if the platform reports it.
+    pub modified: Option<SystemTime>,
+}
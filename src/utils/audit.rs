@@ -0,0 +1,106 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::{ChangeEvent, MooError, MooErrorCodes, MooResult};
+
+/// An append-only log of every change made to a table.
+///
+/// Every change is written as one JSON line with the operation, key, time and new value.
+/// The log is never compacted or truncated, so it keeps a full history of the table.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    /// The file the audit entries are appended to.
+    file: Arc<Mutex<File>>,
+}
+
+/// A single line in the audit log.
+#[derive(Serialize)]
+struct AuditEntry<'a, T, K> {
+    op: &'static str,
+    key: &'a K,
+    timestamp: String,
+    value: Option<&'a T>,
+}
+
+impl AuditLog {
+    /// Open the audit log at `path`, creating it if it doesn't exist yet.
+    pub fn new(path: &Path) -> MooResult<Self> {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Ok(Self {
+                file: Arc::new(Mutex::new(file)),
+            }),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: "Failed to open audit log file. Might be missing permissions to write the directory?".to_string(),
+            }),
+        }
+    }
+
+    /// Append the change events to the audit log.
+    pub fn record<T, K>(&self, events: &[ChangeEvent<T, K>]) -> MooResult<()>
+    where
+        T: Serialize,
+        K: Serialize,
+    {
+        let timestamp = Local::now().to_rfc3339();
+        let mut lines = Vec::new();
+
+        for event in events {
+            let entry = match event {
+                ChangeEvent::Inserted { key, value } => AuditEntry {
+                    op: "insert",
+                    key,
+                    timestamp: timestamp.clone(),
+                    value: Some(value),
+                },
+                ChangeEvent::Updated { key, value } => AuditEntry {
+                    op: "update",
+                    key,
+                    timestamp: timestamp.clone(),
+                    value: Some(value),
+                },
+                ChangeEvent::Deleted { key } => AuditEntry {
+                    op: "delete",
+                    key,
+                    timestamp: timestamp.clone(),
+                    value: None,
+                },
+            };
+
+            match serde_json::to_writer(&mut lines, &entry) {
+                Ok(_) => lines.push(b'\n'),
+                Err(_) => {
+                    return Err(MooError {
+                        code: MooErrorCodes::Error,
+                        message: "Failed to serialize audit log entry.".to_string(),
+                    })
+                }
+            }
+        }
+
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(_) => {
+                return Err(MooError {
+                    code: MooErrorCodes::Fatal,
+                    message: "Failed to lock audit log file.".to_string(),
+                })
+            }
+        };
+
+        match file.write_all(&lines).and_then(|_| file.flush()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(MooError {
+                code: MooErrorCodes::Fatal,
+                message: "The change was saved but writing the audit log failed.".to_string(),
+            }),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::{
     fmt::Debug,
-    fs::{File, OpenOptions},
-    io::Write,
-    path::PathBuf,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
@@ -24,16 +24,35 @@ pub struct DebugClient {
     pub level: DebugLevel,
     /// Whether or not to enable debug mode for the database.
     pub enabled: bool,
+    /// The size the debug file can grow to before it is rotated.
+    pub max_size: Option<u64>,
+    /// Whether messages are sent to the `log` crate instead of the debug file.
+    pub use_log_crate: bool,
+    /// How each debug message is written to the debug file.
+    pub format: DebugFormat,
 }
 
 /// The debug level for the database.
-#[derive(Debug, Clone, Deserialize)]
+///
+/// Levels are ordered from least to most severe, a debug client only logs messages at or above its level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub enum DebugLevel {
     Info,
     Warning,
     Error,
 }
 
+/// How each message is written to the debug file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum DebugFormat {
+    /// A line of text like `[time] Info - "message"`.
+    #[default]
+    Text,
+    /// A JSON object per line like `{"ts":"time","level":"Info","msg":"message"}`,
+    /// which log aggregators can ingest without parsing the text.
+    Json,
+}
+
 impl DebugClient {
     /// Create a new debug client.
     ///
@@ -54,20 +73,27 @@ impl DebugClient {
                 level: d_level,
                 path: None,
                 file: None,
+                max_size: config.max_log_size,
+                use_log_crate: config.use_log_crate,
+                format: config.debug_format,
             };
         }
 
-        let db_dir_path = config.db_dir;
-        let file_path = PathBuf::from(format!("{}/debug.log", db_dir_path));
+        if config.use_log_crate {
+            return Self {
+                enabled: true,
+                level: d_level,
+                path: None,
+                file: None,
+                max_size: config.max_log_size,
+                use_log_crate: true,
+                format: config.debug_format,
+            };
+        }
 
-        println!("Debug file path: {:?}", file_path);
+        let file_path = config.db_dir.join("debug.log");
 
-        let file = match OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&file_path)
-        {
+        let file = match open_log_file(&file_path) {
             Ok(file) => file,
             Err(e) => {
                 println!(
@@ -84,6 +110,9 @@ impl DebugClient {
                             level: d_level,
                             path: None,
                             file: None,
+                            max_size: config.max_log_size,
+                            use_log_crate: false,
+                            format: config.debug_format,
                         };
                     }
                 }
@@ -95,48 +124,292 @@ impl DebugClient {
             level: d_level,
             path: Some(file_path),
             file: Some(Arc::new(Mutex::new(file))),
+            max_size: config.max_log_size,
+            use_log_crate: false,
+            format: config.debug_format,
         }
     }
 
-    // todo - fix log function where logs don't overwrite old logs.
     /// Log a debug message to the debug file.
-    /// 
+    ///
+    /// Messages are appended to the end of the file, once it's bigger than the configured `max_log_size`
+    /// it is moved to `debug.log.1` and a new file is started. Nothing is logged when debug mode is disabled,
+    /// and messages are only printed to stdout when the debug file couldn't be opened.
+    /// With `use_log_crate` enabled the messages go to the `log` crate at the matching level instead.
+    ///
+    /// `level` - The level of the message. Messages below the level of the debug client are skipped.
+    ///
     /// `debug` - The debug message or struct to log. This can be any data type that implements the Debug trait.
-    /// 
+    ///
     /// This function is internal and can't be used outside of the library.
-    pub fn log<T>(&mut self, debug: T)
+    pub fn log<T>(&self, level: DebugLevel, debug: T)
     where
         T: Debug,
     {
-        if !self.enabled {
+        if !self.enabled || level < self.level {
+            return;
+        }
+
+        if self.use_log_crate {
+            match level {
+                DebugLevel::Info => log::info!(target: "moodb", "{:?}", debug),
+                DebugLevel::Warning => log::warn!(target: "moodb", "{:?}", debug),
+                DebugLevel::Error => log::error!(target: "moodb", "{:?}", debug),
+            }
+
             return;
         }
 
+        let line = self.format_line(level, &debug);
+
+        let file = match &self.file {
+            Some(file) => file,
+            None => {
+                println!("{}", line);
+                return;
+            }
+        };
+
+        let mut file = match file.lock() {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+
+        if let (Some(max_size), Some(path)) = (self.max_size, &self.path) {
+            if file.metadata().map_or(0, |metadata| metadata.len()) >= max_size {
+                if let Ok(rotated) = rotate_log_file(path) {
+                    *file = rotated;
+                }
+            }
+        }
+
+        let _ = file.write_all(format!("{}\n", line).as_bytes());
+    }
+
+    /// Formats a debug message as a line in the configured `DebugFormat`, without the newline.
+    ///
+    /// In JSON, string messages are written without the quotes their `Debug` output adds.
+    fn format_line<T>(&self, level: DebugLevel, debug: &T) -> String
+    where
+        T: Debug,
+    {
         let current_time = Local::now();
-        println!("{:?} - {:?}", current_time, debug);
 
-        if let Some(file) = &self.file {
-            let mut file = file.lock().unwrap();
+        match self.format {
+            DebugFormat::Text => format!("[{}] {:?} - {:?}", current_time, level, debug),
+            DebugFormat::Json => {
+                let msg = format!("{:?}", debug);
+                let msg = serde_json::from_str::<String>(&msg).unwrap_or(msg);
 
-            let _ = file.write_all(
-                format!("[{}] {:?} - {:?}\n", current_time, self.level, debug).as_bytes(),
-            );
+                serde_json::json!({
+                    "ts": current_time.to_rfc3339(),
+                    "level": format!("{:?}", level),
+                    "msg": msg,
+                })
+                .to_string()
+            }
         }
     }
 }
 
+impl Drop for DebugClient {
+    /// Flushes the debug file to disk once the last clone of the client is dropped, so no logs are lost.
+    fn drop(&mut self) {
+        let file = match &self.file {
+            Some(file) if Arc::strong_count(file) == 1 => file,
+            _ => return,
+        };
+
+        if let Ok(mut file) = file.lock() {
+            let _ = file.flush();
+            let _ = file.sync_data();
+        }
+    }
+}
+
+/// Opens the debug file for appending, creating it if it doesn't exist yet.
+fn open_log_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Moves the debug file to `<path>.1`, replacing the previous rotated file, and opens a new empty debug file.
+fn rotate_log_file(path: &Path) -> io::Result<File> {
+    let mut rotated_path = path.as_os_str().to_owned();
+    rotated_path.push(".1");
+
+    fs::rename(path, rotated_path)?;
+
+    open_log_file(path)
+}
+
+#[cfg(test)]
 mod tests {
-    #[allow(unused_imports)]
     use super::*;
 
     #[test]
     fn test_debug() {
-        let mut debug = DebugClient::new(true, None, Configuration::default());
+        let debug = DebugClient::new(true, None, Configuration::default());
+
+        for i in 0..100 {
+            debug.log(
+                DebugLevel::Info,
+                format!("Debug index #{} out of #{}", i, 100),
+            );
+        }
+
+        assert!(debug.enabled);
+    }
+
+    #[test]
+    fn test_debug_default_config() {
+        let disabled = DebugClient::new(false, None, Configuration::default());
+        disabled.log(DebugLevel::Error, "Never written");
+
+        assert!(disabled.file.is_none());
+
+        let missing_dir = Configuration {
+            db_dir: PathBuf::from("db/test_debug_default_config/missing"),
+            ..Configuration::default()
+        };
+
+        let _ = fs::remove_dir_all("db/test_debug_default_config");
+
+        let debug = DebugClient::new(true, None, missing_dir);
+        debug.log(DebugLevel::Info, "Nowhere to write");
+
+        assert!(debug.enabled);
+        assert!(debug.file.is_none());
+    }
+
+    #[test]
+    fn test_debug_log_crate() {
+        struct CaptureLogger(Mutex<Vec<String>>);
+
+        impl log::Log for CaptureLogger {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.target() == "moodb"
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    let line = format!("{} {}", record.level(), record.args());
+                    self.0.lock().unwrap().push(line);
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+
+        let config = Configuration {
+            use_log_crate: true,
+            ..Configuration::default()
+        };
+
+        let debug = DebugClient::new(true, None, config);
+
+        debug.log(DebugLevel::Info, "Inserted a record");
+        debug.log(DebugLevel::Error, "Failed to save");
+
+        let lines = LOGGER.0.lock().unwrap();
+
+        assert!(debug.file.is_none());
+        assert!(lines.contains(&"INFO \"Inserted a record\"".to_string()));
+        assert!(lines.contains(&"ERROR \"Failed to save\"".to_string()));
+    }
+
+    #[test]
+    fn test_debug_level() {
+        let dir = "db/test_debug_level";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        let config = Configuration {
+            db_dir: PathBuf::from(dir),
+            ..Configuration::default()
+        };
+
+        let debug = DebugClient::new(true, Some(DebugLevel::Warning), config);
+
+        debug.log(DebugLevel::Info, "Inserted a record");
+        debug.log(DebugLevel::Warning, "No record found");
+        debug.log(DebugLevel::Error, "Failed to save");
+
+        let log = fs::read_to_string(format!("{}/debug.log", dir)).unwrap();
+
+        assert!(!log.contains("Inserted a record"));
+        assert!(log.contains("Warning - \"No record found\""));
+        assert!(log.contains("Error - \"Failed to save\""));
+    }
+
+    #[test]
+    fn test_debug_json_format() {
+        let dir = "db/test_debug_json_format";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        let config = Configuration {
+            db_dir: PathBuf::from(dir),
+            debug_format: DebugFormat::Json,
+            ..Configuration::default()
+        };
+
+        let debug = DebugClient::new(true, None, config);
+
+        debug.log(DebugLevel::Info, "Found record with key: \"1\"");
+        debug.log(DebugLevel::Error, vec![1, 2]);
+
+        let log = fs::read_to_string(format!("{}/debug.log", dir)).unwrap();
+        let lines: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "Info");
+        assert_eq!(lines[0]["msg"], "Found record with key: \"1\"");
+        assert!(lines[0]["ts"].is_string());
+        assert_eq!(lines[1]["level"], "Error");
+        assert_eq!(lines[1]["msg"], "[1, 2]");
+    }
+
+    #[test]
+    fn test_debug_rotation() {
+        let dir = "db/test_debug_rotation";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+
+        let config = Configuration {
+            db_dir: PathBuf::from(dir),
+            max_log_size: Some(512),
+            ..Configuration::default()
+        };
+
+        let debug = DebugClient::new(true, None, config.clone());
 
         for i in 0..100 {
-            debug.log(format!("Debug index #{} out of #{}", i, 100));
+            debug.log(
+                DebugLevel::Info,
+                format!("Debug index #{} out of #{}", i, 100),
+            );
         }
 
-        assert_eq!(debug.enabled, true);
+        let log = fs::read_to_string(format!("{}/debug.log", dir)).unwrap();
+
+        assert!(fs::metadata(format!("{}/debug.log.1", dir)).is_ok());
+        assert!(log.len() < 1024);
+        assert!(log.contains("Debug index #99"));
+
+        // Reopening the debug file keeps the old entries.
+        let debug = DebugClient::new(true, None, config);
+        debug.log(DebugLevel::Info, "Reopened");
+
+        let reopened = fs::read_to_string(format!("{}/debug.log", dir)).unwrap();
+
+        assert!(reopened.starts_with(&log));
     }
 }
@@ -1 +1,3 @@
-pub mod debug;
\ No newline at end of file
+pub mod audit;
+pub mod debug;
+pub mod wal;
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    hash::Hash,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{MooError, MooErrorCodes, MooRecord, MooRecords, MooResult};
+
+/// An append-only log of the changes made to a table since its file was last written.
+///
+/// Saving a table appends one JSON line per changed record instead of rewriting the whole table file.
+/// When the table is opened the log is replayed on top of the table file, and it is emptied every time
+/// the table file is rewritten, like on `compact`.
+#[derive(Debug, Clone)]
+pub struct WriteAheadLog {
+    /// The path to the log file.
+    path: PathBuf,
+}
+
+/// A single line in the write-ahead log.
+#[derive(Serialize, Deserialize)]
+pub enum WalEntry<T, K> {
+    /// A record was inserted or given a new value.
+    Put {
+        key: K,
+        value: T,
+        #[serde(default)]
+        expires_at: Option<i64>,
+        #[serde(default)]
+        deleted: bool,
+    },
+    /// A record was removed from the table.
+    Delete { key: K },
+}
+
+impl WriteAheadLog {
+    /// Use the write-ahead log at `path`. The file is created on the first append.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Append the entries to the log and sync them to disk.
+    pub fn append<T, K>(&self, entries: &[WalEntry<T, K>]) -> MooResult<()>
+    where
+        T: Serialize,
+        K: Serialize,
+    {
+        let mut lines = Vec::new();
+
+        for entry in entries {
+            serde_json::to_writer(&mut lines, entry)?;
+            lines.push(b'\n');
+        }
+
+        let mut file = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+        {
+            Ok(file) => file,
+            Err(_) => {
+                return Err(MooError {
+                    code: MooErrorCodes::Fatal,
+                    message: "Failed to open write-ahead log file. Might be missing permissions to write the directory?".to_string(),
+                })
+            }
+        };
+
+        file.write_all(&lines)?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Apply every entry in the log to the `records` read from the table file.
+    ///
+    /// A line that can't be parsed was torn by a crash in the middle of an append, so it and anything after it is
+    /// cut off the log, which lets the next append start on a clean line.
+    pub fn replay<T, K>(&self, records: MooRecords<T, K>) -> MooResult<MooRecords<T, K>>
+    where
+        T: Serialize + DeserializeOwned,
+        K: Eq + Hash + Clone + Serialize + DeserializeOwned,
+    {
+        let file = match OpenOptions::new().read(true).write(true).open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(records),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut slots: Vec<Option<MooRecord<T, K>>> = records.into_iter().map(Some).collect();
+        let mut positions: HashMap<K, usize> = slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|record| (record.key.clone(), index)))
+            .collect();
+
+        let mut reader = BufReader::new(&file);
+        let mut valid_len = 0;
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            let read = reader.read_line(&mut line)?;
+
+            if read == 0 {
+                break;
+            }
+
+            let entry: WalEntry<T, K> = match serde_json::from_str(&line) {
+                Ok(entry) if line.ends_with('\n') => entry,
+                _ => {
+                    file.set_len(valid_len)?;
+                    break;
+                }
+            };
+
+            valid_len += read as u64;
+
+            match entry {
+                WalEntry::Put {
+                    key,
+                    value,
+                    expires_at,
+                    deleted,
+                } => {
+                    let record = MooRecord {
+                        key: key.clone(),
+                        value,
+                        expires_at,
+                        deleted,
+                    };
+
+                    match positions.get(&key) {
+                        Some(&index) => slots[index] = Some(record),
+                        None => {
+                            positions.insert(key, slots.len());
+                            slots.push(Some(record));
+                        }
+                    }
+                }
+                WalEntry::Delete { key } => {
+                    if let Some(index) = positions.remove(&key) {
+                        slots[index] = None;
+                    }
+                }
+            }
+        }
+
+        Ok(slots.into_iter().flatten().collect())
+    }
+
+    /// Empty the log, after its changes were written to the table file.
+    pub fn truncate(&self) -> MooResult<()> {
+        match fs::remove_file(&self.path) {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The number of bytes the log takes up on disk.
+    pub fn size(&self) -> u64 {
+        fs::metadata(&self.path).map_or(0, |metadata| metadata.len())
+    }
+}